@@ -0,0 +1,109 @@
+use polish_notation::{convert, Evaluator, Notation};
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: pn [--from NOTATION] [--to NOTATION] [EXPRESSION]
+
+calculate EXPRESSION, or write it in another notation with --to.
+without EXPRESSION, every line of the standard input is read as one.
+
+notations: prefix (default), postfix, sexpr, infix";
+
+struct Options {
+    from: Notation,
+    to: Option<Notation>,
+    expression: Option<String>,
+    help: bool,
+}
+
+fn notation(name: Option<String>) -> Result<Notation, String> {
+    let name = name.ok_or("a notation is missing")?;
+    Notation::from_name(&name).ok_or_else(|| format!("unknown notation `{}`", name))
+}
+
+fn options(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        from: Notation::Prefix,
+        to: None,
+        expression: None,
+        help: false,
+    };
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => options.from = notation(args.next())?,
+            "--to" => options.to = Some(notation(args.next())?),
+            "-h" | "--help" => {
+                options.help = true;
+                break;
+            }
+            // 残りはすべて式とする
+            "--" => {
+                options.expression = Some(args.collect::<Vec<_>>().join(" "));
+                break;
+            }
+            _ => {
+                let mut words = vec![arg];
+                words.extend(args.by_ref());
+                options.expression = Some(words.join(" "));
+            }
+        }
+    }
+    Ok(options)
+}
+
+// 結果を標準出力に、エラーを標準エラー出力に書く
+fn run(options: &Options, expression: &str) -> bool {
+    let result = match options.to {
+        Some(to) => convert(expression, options.from, to),
+        None => Evaluator::new()
+            .notation(options.from)
+            .pn(expression)
+            .map(|ans| ans.to_string()),
+    };
+    match result {
+        Ok(out) => {
+            println!("{}", out);
+            true
+        }
+        Err(e) => {
+            eprint!("{}", e.diagnostic(expression));
+            false
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    // 求められた使い方は、エラーではないので標準出力に書く
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let mut ok = true;
+    match &options.expression {
+        Some(expression) => ok = run(&options, expression),
+        None => {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if !line.trim().is_empty() {
+                    ok &= run(&options, &line);
+                }
+            }
+        }
+    }
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
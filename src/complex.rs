@@ -0,0 +1,218 @@
+use crate::{Number, OperatorRegistry};
+use num_complex::Complex64;
+
+/// number literals are real numbers such as `2.5`, imaginary numbers such as `3i`, `-i` or `i`,
+/// and complex numbers such as `2+3i` or `1e-3-2i`, written without spaces.
+/// `re`, `im`, `abs` and `arg` give a real number as a complex number with no imaginary part.
+/// `sqrt -1` is `i`, and `ln`, `sqrt` and `^` give the principal value.
+/// there are no `%`, `//`, `mod`, `min`, `max`, `floor` or other operators that need an order.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, Complex64};
+///
+/// assert_eq!(pn_as::<Complex64>("sqrt -1"), Ok(Complex64::i()));
+/// assert_eq!(pn_as::<Complex64>("* 2+3i 2-3i"), Ok(Complex64::new(13.0, 0.0)));
+/// assert_eq!(pn_as::<Complex64>("conj + 1 3i").unwrap().to_string(), "1-3i");
+/// ```
+impl Number for Complex64 {
+    fn parse_literal(token: &str) -> Option<Self> {
+        let Some(body) = token.strip_suffix('i') else {
+            return f64::parse_literal(token).map(|re| Complex64::new(re, 0.0));
+        };
+        // 実部と虚部を分ける符号を探す。先頭の符号と指数の符号は除く
+        let sign = body
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| i > 0 && (c == '+' || c == '-') && !body[..i].ends_with(['e', 'E']));
+        // `infi` のように inf や nan を含むものは、虚数のリテラルにしない
+        let finite = |token: &str| f64::parse_literal(token).filter(|x| x.is_finite());
+        let (re, im) = match sign {
+            Some((i, _)) => (finite(&body[..i])?, &body[i..]),
+            None => (0.0, body),
+        };
+        // `i` や `-i` は係数 1 を省略したもの
+        let im = match im {
+            "" | "+" => 1.0,
+            "-" => -1.0,
+            im => finite(im)?,
+        };
+        Some(Complex64::new(re, im))
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register_binary("+", |a, b| a + b)
+            .register_binary("-", |a, b| a - b)
+            .register_binary("*", |a, b| a * b)
+            .register_binary("/", |a, b| a / b)
+            .register_binary("^", |a, b| {
+                // 整数乗は掛け算で求めると誤差が少なく、0 の累乗も正しくなる
+                if b.im == 0.0 && b.re.fract() == 0.0 && b.re.abs() <= i32::MAX as f64 {
+                    a.powi(b.re as i32)
+                } else {
+                    a.powc(b)
+                }
+            })
+            .register_unary("neg", |a| -a)
+            .register_unary("abs", |a| Complex64::new(a.norm(), 0.0))
+            .register_unary("arg", |a| Complex64::new(a.arg(), 0.0))
+            .register_unary("re", |a| Complex64::new(a.re, 0.0))
+            .register_unary("im", |a| Complex64::new(a.im, 0.0))
+            .register_unary("conj", |a| a.conj())
+            .register_unary("sqrt", Complex64::sqrt)
+            .register_unary("ln", Complex64::ln)
+            .register_unary("log", Complex64::log10)
+            .register_unary("exp", Complex64::exp)
+            .register_unary("sin", Complex64::sin)
+            .register_unary("cos", Complex64::cos)
+            .register_unary("tan", Complex64::tan)
+            .set_divisor("/", 1)
+            .set_variadic("+")
+            .set_variadic("*");
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    fn is_finite(&self) -> bool {
+        Complex64::is_finite(*self)
+    }
+
+    fn is_nan(&self) -> bool {
+        Complex64::is_nan(*self)
+    }
+
+    fn scan_literal(text: &str) -> Option<usize> {
+        let end = f64::scan_literal(text)?;
+        // すぐ後の `i` は虚数単位。`3in` のように名前が続くなら含めない
+        let imaginary = text[end..]
+            .strip_prefix('i')
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+        Some(end + usize::from(imaginary))
+    }
+
+    // `2+3i` のように、常に実部と虚部の和として書く
+    fn literal_operator(&self) -> Option<&'static str> {
+        Some("+")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn_as, Evaluator, Mode, Notation, PolishError, Span};
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn complex_test() {
+        let c = |re, im| Ok(Complex64::new(re, im));
+        let exoressions = [
+            ("sqrt -1", c(0.0, 1.0)),
+            ("sqrt -4", c(0.0, 2.0)),
+            ("+ 1 3i", c(1.0, 3.0)),
+            ("* 2+3i 2-3i", c(13.0, 0.0)),
+            ("* i i", c(-1.0, 0.0)),
+            ("^ i 2", c(-1.0, 0.0)),
+            ("^ 0 2", c(0.0, 0.0)),
+            ("/ 1 i", c(0.0, -1.0)),
+            ("- -i 1e-1+2.5i", c(-0.1, -3.5)),
+            ("+ 1_000 1e3i", c(1000.0, 1000.0)),
+            ("re 2+3i", c(2.0, 0.0)),
+            ("im 2+3i", c(3.0, 0.0)),
+            ("conj 2+3i", c(2.0, -3.0)),
+            ("abs 3-4i", c(5.0, 0.0)),
+            ("arg i", c(FRAC_PI_2, 0.0)),
+            ("neg 2-i", c(-2.0, 1.0)),
+            ("(+ 1 i 2i)", c(1.0, 3.0)),
+            // 以下エラーテスト
+            (
+                "% 5 2",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "%".to_string(),
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "+ 1 2x",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "2x".to_string(),
+                    span: Span::new(4, 6, 2),
+                }),
+            ),
+            (
+                "+ 1 infi",
+                Err(PolishError::UndefinedVariable {
+                    name: "infi".to_string(),
+                    span: Some(Span::new(4, 8, 2)),
+                }),
+            ),
+            (
+                "+ 1 xi",
+                Err(PolishError::UndefinedVariable {
+                    name: "xi".to_string(),
+                    span: Some(Span::new(4, 6, 2)),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let ans = Evaluator::<Complex64>::default()
+                .notation(Notation::SExpression)
+                .pn(exoression.0);
+            assert_eq!(ans, exoression.1);
+        }
+
+        assert!(matches!(
+            Evaluator::<Complex64>::default()
+                .mode(Mode::Strict)
+                .pn("/ i 0"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+        assert!(pn_as::<Complex64>("/ i 0").unwrap().is_nan());
+    }
+
+    #[test]
+    fn literal_test() {
+        let exoressions = [
+            ("2", Some(Complex64::new(2.0, 0.0))),
+            ("i", Some(Complex64::new(0.0, 1.0))),
+            ("-i", Some(Complex64::new(0.0, -1.0))),
+            ("+2.5i", Some(Complex64::new(0.0, 2.5))),
+            ("-2-i", Some(Complex64::new(-2.0, -1.0))),
+            ("1e+2+1e-2i", Some(Complex64::new(100.0, 0.01))),
+            ("2+3", None),
+            ("2+-3i", None),
+            ("ii", None),
+            ("pi", None),
+            ("infi", None),
+            ("1+nani", None),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(Complex64::parse_literal(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn infix_test() {
+        let registry = OperatorRegistry::<Complex64>::new();
+        let expr = Notation::Infix
+            .parse(&registry, "(2+3i) * (2 - 3i)")
+            .unwrap();
+        assert_eq!(expr.to_string(), "* + 2+0i 0+3i - 2+0i 0+3i");
+        assert_eq!(expr.eval(), Ok(Complex64::new(13.0, 0.0)));
+
+        // 実部と虚部を持つリテラルは、中置記法では括弧で囲む
+        let expr = Notation::Prefix.parse(&registry, "* 2+3i 2").unwrap();
+        assert_eq!(expr.to_infix(), "(2+3i) * (2+0i)");
+        assert_eq!(
+            Notation::Infix
+                .parse(&registry, &expr.to_infix())
+                .and_then(|infix| infix.eval()),
+            expr.eval()
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use crate::number::{nonzero, power_bits};
+use crate::rational::MAX_SCALE;
+use crate::{is_number, Number, OperatorRegistry, PolishError};
+use bigdecimal::{BigDecimal, Context, RoundingMode};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+/// How a decimal result is rounded to the precision of a `DecimalContext`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// to the nearest, and a tie to the even digit. `2.5` is `2`, `3.5` is `4`.
+    #[default]
+    HalfEven,
+    /// to the nearest, and a tie away from zero. `2.5` is `3`, `-2.5` is `-3`.
+    HalfUp,
+    /// toward negative infinity. `2.7` is `2`, `-2.1` is `-3`.
+    Floor,
+    /// toward positive infinity. `2.1` is `3`, `-2.7` is `-2`.
+    Ceiling,
+    /// toward zero. `2.7` is `2`, `-2.7` is `-2`.
+    Truncate,
+}
+
+impl Rounding {
+    fn mode(self) -> RoundingMode {
+        match self {
+            Rounding::HalfEven => RoundingMode::HalfEven,
+            Rounding::HalfUp => RoundingMode::HalfUp,
+            Rounding::Floor => RoundingMode::Floor,
+            Rounding::Ceiling => RoundingMode::Ceiling,
+            Rounding::Truncate => RoundingMode::Down,
+        }
+    }
+}
+
+/// The precision and rounding of decimal arithmetic.
+///
+/// evaluating as `BigDecimal` keeps number literals exactly as they are written, so `+ 0.1 0.2` is `0.3`.
+/// every operator rounds its result to `precision` significant digits with `rounding`.
+/// the registry of a context gives the operators for one evaluation,
+/// and `OperatorRegistry::<BigDecimal>::new` uses the default context,
+/// 34 digits with `Rounding::HalfEven` like IEEE 754 decimal128.
+///
+/// dividing by zero always fails with `DivisionByZero`, and `^` fails with `DomainError`
+/// unless the exponent is an integer. a power too large to compute exactly, such as `^ 3 4000000000`,
+/// is computed with 20 extra digits and then rounded. `round` rounds to an integer with `rounding`.
+/// there are no `ln`, `sin` or other transcendental functions.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, BigDecimal, DecimalContext, Evaluator, Rounding};
+///
+/// let ans = pn_as::<BigDecimal>("+ 0.1 0.2").unwrap();
+/// assert_eq!(ans.to_string(), "0.3");
+///
+/// let registry = DecimalContext::new(4, Rounding::HalfUp).registry();
+/// let ans = Evaluator::default().registry(&registry).pn("/ 2 3").unwrap();
+/// assert_eq!(ans.to_string(), "0.6667");
+///
+/// let registry = DecimalContext::new(4, Rounding::Truncate).registry();
+/// let ans = Evaluator::default().registry(&registry).pn("/ 2 3").unwrap();
+/// assert_eq!(ans.to_string(), "0.6666");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalContext {
+    precision: NonZeroU64,
+    rounding: Rounding,
+}
+
+impl Default for DecimalContext {
+    fn default() -> Self {
+        DecimalContext::new(34, Rounding::HalfEven)
+    }
+}
+
+impl DecimalContext {
+    /// round to `precision` significant digits with `rounding`. a precision of 0 is taken as 1.
+    pub fn new(precision: u64, rounding: Rounding) -> Self {
+        DecimalContext {
+            precision: NonZeroU64::new(precision).unwrap_or(NonZeroU64::MIN),
+            rounding,
+        }
+    }
+
+    pub fn precision(&self) -> u64 {
+        self.precision.get()
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// create a registry of the built-in decimal operators that round with this context.
+    pub fn registry(&self) -> OperatorRegistry<BigDecimal> {
+        let mut registry = OperatorRegistry::empty();
+        self.register(&mut registry);
+        registry
+    }
+
+    fn register(&self, registry: &mut OperatorRegistry<BigDecimal>) {
+        let ctx = Context::new(self.precision, self.rounding.mode());
+        // 演算子ごとに丸め方を持たせる
+        let c = ctx.clone();
+        registry.register_binary("+", move |a, b| round(&c, a + b));
+        let c = ctx.clone();
+        registry.register_binary("-", move |a, b| round(&c, a - b));
+        let c = ctx.clone();
+        registry.register_binary("*", move |a, b| round(&c, a * b));
+        let c = ctx.clone();
+        registry.register("/", 2, move |x| divide(&x[0], nonzero(&x[1])?, &c));
+        let c = ctx.clone();
+        registry.register("%", 2, move |x| Ok(round(&c, &x[0] % nonzero(&x[1])?)));
+        let c = ctx.clone();
+        registry.register("//", 2, move |x| {
+            Ok(round(&c, floor_divide(&x[0], nonzero(&x[1])?)))
+        });
+        let c = ctx.clone();
+        registry.register("mod", 2, move |x| {
+            let b = nonzero(&x[1])?;
+            let r = &x[0] % b;
+            // 余りはいつも 0 以上にする
+            if r.is_negative() {
+                Ok(round(&c, r + b.abs()))
+            } else {
+                Ok(round(&c, r))
+            }
+        });
+        let c = ctx.clone();
+        registry.register("^", 2, move |x| {
+            let exp = x[1]
+                .is_integer()
+                .then(|| x[1].to_i64())
+                .flatten()
+                .ok_or_else(domain_error)?;
+            power(&x[0], exp, &c)
+        });
+        let c = ctx.clone();
+        registry.register("sqrt", 1, move |x| {
+            let root = x[0].sqrt_with_context(&c).ok_or_else(domain_error)?;
+            Ok(trim(root, (x[0].fractional_digit_count() + 1) / 2))
+        });
+        let c = ctx.clone();
+        registry.register_unary("neg", move |a| round(&c, -a));
+        let c = ctx.clone();
+        registry.register_unary("abs", move |a| round(&c, a.abs()));
+        let c = ctx.clone();
+        registry.register_unary("floor", move |a| {
+            round(&c, a.with_scale_round(0, RoundingMode::Floor))
+        });
+        let c = ctx.clone();
+        registry.register_unary("ceil", move |a| {
+            round(&c, a.with_scale_round(0, RoundingMode::Ceiling))
+        });
+        let mode = self.rounding.mode();
+        registry.register_unary("round", move |a| round(&ctx, a.with_scale_round(0, mode)));
+        registry
+            .register_binary("min", Ord::min)
+            .register_binary("max", Ord::max)
+            .register("clamp", 3, move |x| {
+                if x[1] <= x[2] {
+                    Ok(x[0].clone().clamp(x[1].clone(), x[2].clone()))
+                } else {
+                    Err(domain_error())
+                }
+            })
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+}
+
+// 精度より桁が多いときだけ丸める。round_decimal は足りない桁を 0 で埋めてしまう
+fn round(ctx: &Context, n: BigDecimal) -> BigDecimal {
+    if n.digits() > ctx.precision().get() {
+        ctx.round_decimal(n)
+    } else {
+        n
+    }
+}
+
+// 割り切れた結果から、scale の桁までの余分な 0 を取り除く
+fn trim(n: BigDecimal, scale: i64) -> BigDecimal {
+    let (mut digits, mut n_scale) = n.into_bigint_and_scale();
+    while n_scale > scale.max(0) && Zero::is_zero(&(&digits % 10u8)) {
+        digits /= 10u8;
+        n_scale -= 1;
+    }
+    BigDecimal::new(digits, n_scale)
+}
+
+fn domain_error() -> PolishError {
+    PolishError::DomainError {
+        operator: None,
+        span: None,
+    }
+}
+
+// 整数の指数で累乗する。負の指数は逆数を割り算で求める
+fn power(base: &BigDecimal, exp: i64, ctx: &Context) -> Result<BigDecimal, PolishError> {
+    let e = u32::try_from(exp.unsigned_abs()).map_err(|_| domain_error())?;
+    let (digits, scale) = base.as_bigint_and_exponent();
+    let value = if power_bits(digits.bits(), u64::from(e)).is_ok() {
+        let scale = scale.checked_mul(i64::from(e)).ok_or_else(domain_error)?;
+        BigDecimal::new(digits.pow(e), scale)
+    } else {
+        // 正確な結果が大きすぎるときは、精度に余裕を持たせて丸めながら二乗を繰り返す
+        let work = Context::new(ctx.precision().saturating_add(20), RoundingMode::HalfEven);
+        let mut value = BigDecimal::from(1);
+        let mut square = base.clone();
+        let mut e = e;
+        loop {
+            if e & 1 == 1 {
+                value = multiply(&value, &square, &work)?;
+            }
+            e >>= 1;
+            if e == 0 {
+                break value;
+            }
+            square = multiply(&square, &square, &work)?;
+        }
+    };
+    if exp >= 0 {
+        Ok(round(ctx, value))
+    } else {
+        divide(&BigDecimal::from(1), nonzero(&value)?, ctx)
+    }
+}
+
+// 掛けてから丸める。積の scale が i64 に収まらなければ DomainError にする
+fn multiply(a: &BigDecimal, b: &BigDecimal, ctx: &Context) -> Result<BigDecimal, PolishError> {
+    let (m1, s1) = a.as_bigint_and_exponent();
+    let (m2, s2) = b.as_bigint_and_exponent();
+    let scale = s1.checked_add(s2).ok_or_else(domain_error)?;
+    Ok(round(ctx, BigDecimal::new(m1 * m2, scale)))
+}
+
+fn digits(n: &BigInt) -> i64 {
+    n.magnitude().to_string().len() as i64
+}
+
+// 精度より多めの桁まで割り、割り切れなければ最後の桁に 1 を置いてから丸める。
+// こうすると、ちょうど半分かどうかが丸めるときにも分かる。商の scale が i64 に収まらなければ DomainError にする
+fn divide(a: &BigDecimal, b: &BigDecimal, ctx: &Context) -> Result<BigDecimal, PolishError> {
+    let (m1, s1) = a.as_bigint_and_exponent();
+    let (m2, s2) = b.as_bigint_and_exponent();
+    if Zero::is_zero(&m1) {
+        return Ok(BigDecimal::zero());
+    }
+    let precision = ctx.precision().get() as i64;
+    let shift = (precision + 1 + digits(&m2) - digits(&m1)).max(0);
+    let scale = s1.checked_sub(s2).ok_or_else(domain_error)?;
+    let shifted = scale.checked_add(shift).ok_or_else(domain_error)?;
+    let n = m1 * BigInt::from(10).pow(shift as u32);
+    let (q, r) = n.div_rem(&m2);
+    if Zero::is_zero(&r) {
+        return Ok(round(ctx, trim(BigDecimal::new(q, shifted), scale)));
+    }
+    let sticky = if n.is_negative() != m2.is_negative() {
+        -1
+    } else {
+        1
+    };
+    let shifted = shifted.checked_add(1).ok_or_else(domain_error)?;
+    Ok(round(ctx, BigDecimal::new(q * 10 + sticky, shifted)))
+}
+
+// 桁をそろえてから整数として割る。|a| < |b| なら桁をそろえなくても商は 0 か -1
+fn floor_divide(a: &BigDecimal, b: &BigDecimal) -> BigDecimal {
+    if a.abs() < b.abs() {
+        return if Zero::is_zero(a) || a.is_negative() == b.is_negative() {
+            BigDecimal::zero()
+        } else {
+            BigDecimal::from(-1)
+        };
+    }
+    let scale = a.fractional_digit_count().max(b.fractional_digit_count());
+    let (m1, _) = a.with_scale(scale).into_bigint_and_exponent();
+    let (m2, _) = b.with_scale(scale).into_bigint_and_exponent();
+    BigDecimal::from(m1.div_floor(&m2))
+}
+
+/// number literals are read exactly, without rounding. the operators round with `DecimalContext::default`.
+/// a literal with an exponent above 10000, such as `1e20000`, is not a number.
+/// an operator whose result has an exponent that does not fit in `i64` fails with `DomainError`.
+impl Number for BigDecimal {
+    fn parse_literal(token: &str) -> Option<Self> {
+        // inf と nan は BigDecimal にならない
+        if !is_number(token) {
+            return None;
+        }
+        let token = token.replace('_', "");
+        // 指数の大きすぎるリテラルは、桁をそろえるときに巨大な整数になるので読まない
+        if let Some((_, exponent)) = token.split_once(['e', 'E']) {
+            exponent
+                .parse::<i64>()
+                .ok()
+                .filter(|exponent| exponent.unsigned_abs() <= u64::from(MAX_SCALE))?;
+        }
+        BigDecimal::from_str(&token).ok()
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        DecimalContext::default().register(registry);
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn_as, Evaluator, Span};
+
+    #[test]
+    fn decimal_test() {
+        let exoressions = [
+            ("+ 0.1 0.2", Ok("0.3")),
+            ("* 1.10 3", Ok("3.30")),
+            ("- 1 0.01", Ok("0.99")),
+            ("/ 1 4", Ok("0.25")),
+            ("/ 1 3", Ok("0.3333333333333333333333333333333333")),
+            ("/ -2 3", Ok("-0.6666666666666666666666666666666667")),
+            ("// -7 2", Ok("-4")),
+            ("// 7.5 0.2", Ok("37")),
+            ("% -7 2", Ok("-1")),
+            ("mod -7 2", Ok("1")),
+            ("mod 7.5 -2", Ok("1.5")),
+            ("^ 1.1 2", Ok("1.21")),
+            ("^ 2 -2", Ok("0.25")),
+            (
+                "^ 3 4000000000",
+                Ok("7562227687355548530201943986629278e+1908484985"),
+            ),
+            (
+                "^ 3 -4000000000",
+                Ok("1.322361665560604305223762105809891E-1908485019"),
+            ),
+            ("^ 1.0001 100000", Ok("22015.45604855219864570145658165872")),
+            ("sqrt 2.25", Ok("1.5")),
+            ("round 2.5", Ok("2")),
+            ("round 3.5", Ok("4")),
+            ("floor -2.5", Ok("-3")),
+            ("ceil 2.1", Ok("3")),
+            ("max 0.1 neg -0.2", Ok("0.2")),
+            ("+ 1_000.5 .5", Ok("1001.0")),
+            ("// 1e-10000 3", Ok("0")),
+            ("// -1e-10000 3", Ok("-1")),
+            // 以下エラーテスト
+            (
+                "/ 1 0.0",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("/".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 0 -1",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 2 0.5",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "sqrt -1",
+                Err(PolishError::DomainError {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "+ 1 nan",
+                Err(PolishError::UndefinedVariable {
+                    name: "nan".to_string(),
+                    span: Some(Span::new(4, 7, 2)),
+                }),
+            ),
+            (
+                "^ ^ 1e10000 4000000000 4000000000",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "/ ^ ^ 1e-10000 4000000000 200000 ^ ^ 1e10000 4000000000 200000",
+                Err(PolishError::DomainError {
+                    operator: Some("/".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 1e9000000000000000000",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1e9000000000000000000".to_string(),
+                    span: Span::new(4, 25, 2),
+                }),
+            ),
+            (
+                "/ 1 1e-9223372036854775807",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1e-9223372036854775807".to_string(),
+                    span: Span::new(4, 26, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(
+                pn_as::<BigDecimal>(exoression.0).map(|ans| ans.to_string()),
+                exoression.1.map(str::to_string)
+            );
+        }
+    }
+
+    #[test]
+    fn rounding_test() {
+        let exoressions = [
+            ("/ 5 2", Rounding::HalfEven, "2"),
+            ("/ 7 2", Rounding::HalfEven, "4"),
+            ("/ 5 2", Rounding::HalfUp, "3"),
+            ("/ -5 2", Rounding::HalfUp, "-3"),
+            ("/ 5 2", Rounding::Floor, "2"),
+            ("/ -5 2", Rounding::Floor, "-3"),
+            ("/ 5 2", Rounding::Ceiling, "3"),
+            ("/ -5 2", Rounding::Ceiling, "-2"),
+            ("/ 5 2", Rounding::Truncate, "2"),
+            ("/ -5 2", Rounding::Truncate, "-2"),
+            // 割り切れないときは、半分より少しでも大きければ切り上げる
+            ("/ 2500001 1000000", Rounding::HalfEven, "3"),
+            ("* 2.5 1", Rounding::HalfEven, "2"),
+            ("+ 0.5 2", Rounding::HalfUp, "3"),
+            ("round 2.5", Rounding::HalfUp, "3"),
+            ("round 2.5", Rounding::HalfEven, "2"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let registry = DecimalContext::new(1, exoression.1).registry();
+            let ans = Evaluator::default()
+                .registry(&registry)
+                .pn(exoression.0)
+                .unwrap();
+            assert_eq!(ans.to_string(), exoression.2);
+        }
+
+        let context = DecimalContext::new(0, Rounding::Floor);
+        assert_eq!(context.precision(), 1);
+        assert_eq!(context.rounding(), Rounding::Floor);
+        let registry = DecimalContext::new(50, Rounding::HalfEven).registry();
+        let ans = Evaluator::default()
+            .registry(&registry)
+            .pn("/ 1 7")
+            .unwrap();
+        assert_eq!(
+            ans.to_string(),
+            "0.14285714285714285714285714285714285714285714285714"
+        );
+    }
+}
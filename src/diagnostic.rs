@@ -0,0 +1,353 @@
+use crate::{PolishError, Span};
+use std::fmt;
+
+/// A rustc-style report of a `PolishError`.
+///
+/// it shows the error code and message, the line of the exoression with a caret under the token
+/// that caused the error, and a hint on how to fix it.
+/// errors without a span, such as `NotEnteredExoression`, are shown without the caret.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::pn;
+///
+/// let expression = "* + 5 1 - 7";
+/// let error = pn(expression).unwrap_err();
+/// assert_eq!(
+///     error.diagnostic(expression).to_string(),
+///     "\
+/// error[E0002]: not enough operands for `-`: expected 2, found 1
+///  --> 1:9
+///   |
+/// 1 | * + 5 1 - 7
+///   |         ^ operator `-` needs 2 operands but found 1
+///   |
+///   = help: `-` needs 1 more operand
+/// "
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic<'a> {
+    error: &'a PolishError,
+    expression: &'a str,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// create a report of `error`, which came from `expression`.
+    pub fn new(error: &'a PolishError, expression: &'a str) -> Self {
+        Diagnostic { error, expression }
+    }
+
+    /// the error code, same as `PolishError::code`.
+    pub fn code(&self) -> &'static str {
+        self.error.code()
+    }
+
+    /// the text shown next to the caret.
+    pub fn label(&self) -> String {
+        match self.error {
+            PolishError::FailedCalculate { .. } => "the calculation failed here".to_string(),
+            PolishError::NotEnoughOperands {
+                operator,
+                expected,
+                found,
+                ..
+            } => format!(
+                "operator `{}` needs {} but found {}",
+                operator,
+                operands(*expected),
+                found
+            ),
+            PolishError::UseUnavailableCharacter { token, .. } => {
+                format!("`{}` is not a number, operator or variable", token)
+            }
+            PolishError::NotEnteredExoression => "the exoression is empty".to_string(),
+            PolishError::UndefinedVariable { name, .. } => format!("`{}` has no value", name),
+            PolishError::UnknownOperator { operator, .. } => {
+                format!("`{}` is not in the operator registry", operator)
+            }
+            PolishError::TooManyOperands { remaining, .. } => {
+                format!("{} not used by any operator", values(*remaining))
+            }
+            PolishError::DivisionByZero { .. } => "the divisor is zero".to_string(),
+            PolishError::DomainError { .. } => "the operands are out of range".to_string(),
+            PolishError::Overflow {
+                operator: Some(_), ..
+            } => "the result is too large for a finite number".to_string(),
+            PolishError::NotANumber {
+                operator: Some(_), ..
+            } => "the result is NaN".to_string(),
+            PolishError::Overflow { operator: None, .. } => "this value is infinite".to_string(),
+            PolishError::NotANumber { operator: None, .. } => "this value is NaN".to_string(),
+            PolishError::UnbalancedParenthesis { .. } => "this parenthesis has no pair".to_string(),
+            PolishError::UnexpectedToken { token, .. } => {
+                format!("`{}` cannot come here", token)
+            }
+            PolishError::IntegerOverflow { .. } => {
+                "the result does not fit in the integer type".to_string()
+            }
+            PolishError::DimensionMismatch {
+                expected, found, ..
+            } => format!("expected `{}`, found `{}`", expected, found),
+        }
+    }
+
+    /// a hint on how to fix the error.
+    pub fn help(&self) -> String {
+        match self.error {
+            PolishError::FailedCalculate { .. } => {
+                "check the operands of the operator, and that the exoression reduces to one value"
+                    .to_string()
+            }
+            PolishError::NotEnoughOperands {
+                operator,
+                expected,
+                found,
+                ..
+            } => format!(
+                // 後置記法では被演算子が演算子の前に来るので、位置は言わない
+                "`{}` needs {} more {}",
+                operator,
+                expected.saturating_sub(*found),
+                if expected.saturating_sub(*found) == 1 {
+                    "operand"
+                } else {
+                    "operands"
+                }
+            ),
+            PolishError::UseUnavailableCharacter { .. } => {
+                "numbers look like `1.5` or `6.02e23`, and variables are letters, digits and `_`"
+                    .to_string()
+            }
+            PolishError::NotEnteredExoression => "enter an exoression such as `+ 5 1`".to_string(),
+            PolishError::UndefinedVariable { name, .. } => {
+                format!("set `{}` in the `Environment` before evaluating", name)
+            }
+            PolishError::UnknownOperator { operator, .. } => format!(
+                "register `{}` in the `OperatorRegistry` used for evaluation",
+                operator
+            ),
+            PolishError::TooManyOperands { .. } => {
+                "add an operator in front, or remove the extra operands".to_string()
+            }
+            PolishError::DivisionByZero { .. } => "make sure the divisor is not zero".to_string(),
+            PolishError::DomainError { operator, .. } => match operator.as_deref() {
+                Some("clamp") => {
+                    "the lower bound of `clamp` must not be above the upper bound".to_string()
+                }
+                Some(operator) => format!("check the values `{}` accepts", operator),
+                None => "check the values the operator accepts".to_string(),
+            },
+            PolishError::Overflow { .. } | PolishError::NotANumber { .. } => {
+                "strict mode only accepts finite numbers, use `Mode::Permissive` to allow them"
+                    .to_string()
+            }
+            PolishError::UnbalancedParenthesis { .. } => {
+                "add the matching parenthesis, or remove this one".to_string()
+            }
+            PolishError::UnexpectedToken { .. } => {
+                "operators go between operands, and a function name is followed by `(`".to_string()
+            }
+            PolishError::IntegerOverflow { .. } => {
+                "evaluate as `BigInt` to use integers of any size".to_string()
+            }
+            PolishError::DimensionMismatch { .. } => {
+                "give the operator operands of the dimension it expects".to_string()
+            }
+        }
+    }
+}
+
+fn values(n: usize) -> String {
+    if n == 1 {
+        "1 value is".to_string()
+    } else {
+        format!("{} values are", n)
+    }
+}
+
+fn operands(n: usize) -> String {
+    if n == 1 {
+        "1 operand".to_string()
+    } else {
+        format!("{} operands", n)
+    }
+}
+
+// span のある行と、その行の中での位置 (文字単位)
+struct Location<'a> {
+    line_number: usize,
+    line: &'a str,
+    column: usize,
+    width: usize,
+}
+
+fn locate(expression: &str, span: Span) -> Location<'_> {
+    let start = span.start.min(expression.len());
+    let end = span.end.clamp(start, expression.len());
+    let line_start = expression[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = expression[start..]
+        .find('\n')
+        .map_or(expression.len(), |i| start + i);
+    Location {
+        line_number: expression[..line_start].matches('\n').count() + 1,
+        line: expression[line_start..line_end].trim_end_matches('\r'),
+        column: expression[line_start..start].chars().count(),
+        width: expression[start..end.min(line_end)].chars().count().max(1),
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error[{}]: {}", self.code(), self.error.message())?;
+        match self.error.span() {
+            Some(span) => {
+                let location = locate(self.expression, span);
+                let gutter = " ".repeat(location.line_number.to_string().len());
+                writeln!(
+                    f,
+                    "{}--> {}:{}",
+                    gutter,
+                    location.line_number,
+                    location.column + 1
+                )?;
+                writeln!(f, "{} |", gutter)?;
+                // タブは幅がそろわないので空白にする
+                writeln!(
+                    f,
+                    "{} | {}",
+                    location.line_number,
+                    location.line.replace('\t', " ")
+                )?;
+                writeln!(
+                    f,
+                    "{} | {}{} {}",
+                    gutter,
+                    " ".repeat(location.column),
+                    "^".repeat(location.width),
+                    self.label()
+                )?;
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} = help: {}", gutter, self.help())
+            }
+            None => {
+                writeln!(f, "  = note: {}", self.label())?;
+                writeln!(f, "  = help: {}", self.help())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, pn, Environment, Evaluator, Expr, Notation};
+
+    #[test]
+    fn render_test() {
+        let expression = "+ 1 $$ 2";
+        let error = pn(expression).unwrap_err();
+        assert_eq!(
+            error.diagnostic(expression).to_string(),
+            "\
+error[E0003]: use unavailable character in `$$`
+ --> 1:5
+  |
+1 | + 1 $$ 2
+  |     ^^ `$$` is not a number, operator or variable
+  |
+  = help: numbers look like `1.5` or `6.02e23`, and variables are letters, digits and `_`
+"
+        );
+
+        let expression = "clamp 1";
+        let error = pn(expression).unwrap_err();
+        assert_eq!(
+            error.diagnostic(expression).to_string(),
+            "\
+error[E0002]: not enough operands for `clamp`: expected 3, found 1
+ --> 1:1
+  |
+1 | clamp 1
+  | ^^^^^ operator `clamp` needs 3 operands but found 1
+  |
+  = help: `clamp` needs 2 more operands
+"
+        );
+
+        // 後置記法では被演算子が演算子の前に来るので、ヒントは位置を言わない
+        let expression = "1 -";
+        let error = Evaluator::new()
+            .notation(Notation::Postfix)
+            .pn(expression)
+            .unwrap_err();
+        assert_eq!(
+            error.diagnostic(expression).help(),
+            "`-` needs 1 more operand"
+        );
+
+        let error = pn("").unwrap_err();
+        assert_eq!(
+            error.diagnostic("").to_string(),
+            "\
+error[E0004]: not entered exoression
+  = note: the exoression is empty
+  = help: enter an exoression such as `+ 5 1`
+"
+        );
+    }
+
+    #[test]
+    fn too_many_operands_test() {
+        let expression = "+ 1 2 3 4";
+        let error = pn(expression).unwrap_err();
+        assert_eq!(
+            error.diagnostic(expression).to_string(),
+            "\
+error[E0007]: too many operands: 2 left over
+ --> 1:7
+  |
+1 | + 1 2 3 4
+  |       ^ 2 values are not used by any operator
+  |
+  = help: add an operator in front, or remove the extra operands
+"
+        );
+    }
+
+    #[test]
+    fn multi_line_test() {
+        let expression = "+ x\n\t* 2 y";
+        let error = parse(expression)
+            .unwrap()
+            .eval_in(Environment::new().set("x", 1.0))
+            .unwrap_err();
+        assert_eq!(
+            error.diagnostic(expression).to_string(),
+            "\
+error[E0005]: undefined variable `y`
+ --> 2:6
+  |
+2 |  * 2 y
+  |      ^ `y` has no value
+  |
+  = help: set `y` in the `Environment` before evaluating
+"
+        );
+    }
+
+    #[test]
+    fn without_span_test() {
+        let error = Expr::variable("x").eval().unwrap_err();
+        let diagnostic = error.diagnostic("x");
+        assert_eq!(diagnostic.code(), "E0005");
+        assert_eq!(
+            diagnostic.to_string(),
+            "\
+error[E0005]: undefined variable `x`
+  = note: `x` has no value
+  = help: set `x` in the `Environment` before evaluating
+"
+        );
+    }
+}
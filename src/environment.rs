@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+/// The values of the variables in an expression.
+///
+/// an identifier that is not a registered operator is read as a variable,
+/// and evaluation looks its value up here.
+/// a variable that is not set makes evaluation fail with `PolishError::UndefinedVariable`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{parse, Environment};
+///
+/// let expr = parse("+ x * 2 y").unwrap();
+/// let mut env = Environment::new();
+/// env.set("y", 10.0);
+///
+/// for x in [1.0, 2.0, 3.0] {
+///     env.set("x", x);
+///     assert_eq!(expr.eval_in(&env), Ok(x + 20.0));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment<N = f64> {
+    variables: HashMap<String, N>,
+}
+
+impl<N: Clone> Environment<N> {
+    /// create an environment without any variables.
+    pub fn new() -> Self {
+        Environment {
+            variables: HashMap::new(),
+        }
+    }
+
+    /// set the value of `name`, replacing the old value if there is one.
+    pub fn set(&mut self, name: &str, value: N) -> &mut Self {
+        self.variables.insert(name.to_string(), value);
+        self
+    }
+
+    /// the value of `name`, or `None` when it is not set.
+    pub fn get(&self, name: &str) -> Option<N> {
+        self.variables.get(name).cloned()
+    }
+
+    /// remove `name`, then return its old value.
+    pub fn remove(&mut self, name: &str) -> Option<N> {
+        self.variables.remove(name)
+    }
+
+    /// whether `name` is set.
+    pub fn contains(&self, name: &str) -> bool {
+        self.variables.contains_key(name)
+    }
+}
+
+impl<N: Clone> Default for Environment<N> {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+impl<S: Into<String>, N> FromIterator<(S, N)> for Environment<N> {
+    fn from_iter<I: IntoIterator<Item = (S, N)>>(iter: I) -> Self {
+        Environment {
+            variables: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, pn, pn_with, Expr, OperatorRegistry, PolishError, Span};
+
+    #[test]
+    fn variable_test() {
+        let env: Environment = [("x", 3.0), ("y", 4.0), ("rate_2", 0.5)]
+            .into_iter()
+            .collect();
+        let exoressions = [
+            ("x", Ok(3.0)),
+            ("+ x * 2 y", Ok(11.0)),
+            ("* rate_2 - y x", Ok(0.5)),
+            ("sqrt + * x x * y y", Ok(5.0)),
+            (
+                "_ignored",
+                Err(PolishError::UndefinedVariable {
+                    name: "_ignored".to_string(),
+                    span: Some(Span::new(0, 8, 0)),
+                }),
+            ),
+            (
+                "+ x z",
+                Err(PolishError::UndefinedVariable {
+                    name: "z".to_string(),
+                    span: Some(Span::new(4, 5, 2)),
+                }),
+            ),
+            (
+                "+ x 2y",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "2y".to_string(),
+                    span: Span::new(4, 6, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let result = parse(exoression.0).and_then(|expr| expr.eval_in(&env));
+            assert_eq!(result, exoression.1);
+        }
+    }
+
+    #[test]
+    fn parse_variable_test() {
+        assert_eq!(
+            parse("- x 1"),
+            Ok(Expr::operator(
+                "-",
+                vec![Expr::variable("x"), Expr::number(1.0)]
+            ))
+        );
+        // inf や nan は変数ではなく数値として読む
+        assert_eq!(parse("inf"), Ok(Expr::number(f64::INFINITY)));
+        assert_eq!(
+            pn("x"),
+            Err(PolishError::UndefinedVariable {
+                name: "x".to_string(),
+                span: Some(Span::new(0, 1, 0)),
+            })
+        );
+    }
+
+    #[test]
+    fn operator_name_test() {
+        // 演算子として登録された名前は変数にならない
+        let mut registry = OperatorRegistry::new();
+        registry.register("pi", 0, |_| Ok(std::f64::consts::PI));
+        assert_eq!(pn_with(&registry, "pi"), Ok(std::f64::consts::PI));
+        assert_eq!(
+            pn("pi"),
+            Err(PolishError::UndefinedVariable {
+                name: "pi".to_string(),
+                span: Some(Span::new(0, 2, 0)),
+            })
+        );
+
+        let mut env = Environment::new();
+        env.set("pi", 3.0);
+        let expr = parse("pi").unwrap();
+        assert_eq!(expr.eval_in(&env), Ok(3.0));
+        assert_eq!(env.remove("pi"), Some(3.0));
+        assert!(!env.contains("pi"));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::registry::default_registry;
+use crate::{
+    Environment, EvalObserver, Expr, ExprKind, Notation, Number, OperatorRegistry, PolishError,
+    Span,
+};
+
+/// How `Evaluator` treats division by zero, infinity and NaN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// follow IEEE 754 like `f64` does, so `/ 1 0` is `inf` and `% 1 0` is `NaN`.
+    #[default]
+    Permissive,
+    /// every value must be a finite number.
+    ///
+    /// - a divisor that is zero fails with `DivisionByZero`.
+    /// - a result that is infinite fails with `Overflow`, and a result that is NaN fails with `NotANumber`.
+    /// - a number literal or a variable that is infinite or NaN fails the same way, without an operator.
+    Strict,
+}
+
+/// Evaluates an `Expr` with a chosen registry, environment, `Mode` and `Notation`.
+///
+/// `Expr::eval`, `Expr::eval_in` and `Expr::eval_with` are shorthands for this
+/// with the built-in operators, no variables and `Mode::Permissive`.
+///
+/// `Evaluator::new` evaluates as `f64`. for another `Number` type,
+/// start from `Evaluator::<N>::default()`, such as `Evaluator::<i64>::default()`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{Evaluator, Mode, PolishError};
+///
+/// assert_eq!(Evaluator::new().pn("/ 1 0"), Ok(f64::INFINITY));
+///
+/// let strict = Evaluator::new().mode(Mode::Strict);
+/// assert!(matches!(
+///     strict.pn("/ 1 0"),
+///     Err(PolishError::DivisionByZero { .. })
+/// ));
+/// assert!(matches!(
+///     strict.pn("sqrt -1"),
+///     Err(PolishError::NotANumber { .. })
+/// ));
+/// ```
+#[derive(Debug)]
+pub struct Evaluator<'a, N = f64> {
+    registry: &'a OperatorRegistry<N>,
+    env: Option<&'a Environment<N>>,
+    mode: Mode,
+    notation: Notation,
+}
+
+impl Evaluator<'_> {
+    /// create an evaluator with the built-in operators of `f64`, no variables, `Mode::Permissive`
+    /// and `Notation::Prefix`.
+    pub fn new() -> Self {
+        Evaluator::default()
+    }
+}
+
+impl<'a, N: Number> Evaluator<'a, N> {
+    /// look operators up in `registry`.
+    pub fn registry(mut self, registry: &'a OperatorRegistry<N>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// look variables up in `env`.
+    pub fn env(mut self, env: &'a Environment<N>) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// set how division by zero, infinity and NaN are treated.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// set the notation `Evaluator::pn` reads, and the order in which tokens are evaluated.
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    /// parse `expression` with the registry of this evaluator, then evaluate it.
+    pub fn pn(&self, expression: &str) -> Result<N, PolishError> {
+        self.eval(&self.notation.parse(self.registry, expression)?)
+    }
+
+    /// calculate `expr`, then return ans as `N` or `PolishError`.
+    pub fn eval(&self, expr: &Expr<N>) -> Result<N, PolishError> {
+        self.eval_observed(expr, &mut ())
+    }
+
+    /// same as `Evaluator::eval`, but reports every step to `observer`.
+    pub fn eval_observed(
+        &self,
+        expr: &Expr<N>,
+        observer: &mut dyn EvalObserver<N>,
+    ) -> Result<N, PolishError> {
+        let mut operands: Vec<N> = vec![];
+        match self.visit(expr, &mut operands, observer) {
+            Ok(()) => Ok(operands.remove(operands.len() - 1)),
+            Err(e) => {
+                observer.on_error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    /// same as `Evaluator::pn`, but reports every step to `observer`.
+    pub fn pn_observed(
+        &self,
+        expression: &str,
+        observer: &mut dyn EvalObserver<N>,
+    ) -> Result<N, PolishError> {
+        match self.notation.parse(self.registry, expression) {
+            Ok(expr) => self.eval_observed(&expr, observer),
+            Err(e) => {
+                observer.on_error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    // 前置記法は元の pn と同じく右のトークンから、後置記法と中置記法は左のトークンから読み、被演算子をスタックに積む。
+    // 深い木でもスタックが溢れないよう、再帰せずにこれから辿る節を積んでおく
+    fn visit(
+        &self,
+        expr: &Expr<N>,
+        operands: &mut Vec<N>,
+        observer: &mut dyn EvalObserver<N>,
+    ) -> Result<(), PolishError> {
+        let mut work = vec![Visit::Enter(expr)];
+        while let Some(visit) = work.pop() {
+            let value = match visit {
+                Visit::Enter(expr) => match &expr.kind {
+                    // 被演算子の場合
+                    ExprKind::Number(n) => {
+                        observer.on_token(expr);
+                        self.check(n.clone(), None, expr.span)?
+                    }
+                    // 変数の場合
+                    ExprKind::Variable(name) => {
+                        observer.on_token(expr);
+                        let value = self.env.and_then(|env| env.get(name)).ok_or_else(|| {
+                            PolishError::UndefinedVariable {
+                                name: name.clone(),
+                                span: expr.span,
+                            }
+                        })?;
+                        self.check(value, None, expr.span)?
+                    }
+                    // 演算子の場合は、被演算子を先に辿る
+                    ExprKind::Operator { op, children } => {
+                        work.push(Visit::Apply(expr, op, children.len()));
+                        if self.notation.left_to_right() {
+                            work.extend(children.iter().rev().map(Visit::Enter));
+                        } else {
+                            work.extend(children.iter().map(Visit::Enter));
+                        }
+                        continue;
+                    }
+                },
+                Visit::Apply(expr, op, found) => self.apply(expr, op, found, operands, observer)?,
+            };
+            operands.push(value.clone());
+            observer.on_push(value, operands);
+        }
+        Ok(())
+    }
+
+    // 被演算子を辿り終えた演算子を、スタックの上の値に適用する
+    fn apply(
+        &self,
+        expr: &Expr<N>,
+        op: &str,
+        found: usize,
+        operands: &mut Vec<N>,
+        observer: &mut dyn EvalObserver<N>,
+    ) -> Result<N, PolishError> {
+        observer.on_token(expr);
+        let operator = self.registry.get(op).ok_or(PolishError::UnknownOperator {
+            operator: op.to_string(),
+            span: expr.span,
+        })?;
+        if found < operator.arity() {
+            return Err(PolishError::NotEnoughOperands {
+                operator: op.to_string(),
+                expected: operator.arity(),
+                found,
+                span: expr.span,
+            });
+        }
+        let mut taken = operands.split_off(operands.len() - found);
+        if !self.notation.left_to_right() {
+            taken.reverse();
+        }
+        if self.mode == Mode::Strict {
+            if let Some(divisor) = operator.divisor() {
+                if taken.get(divisor).is_some_and(N::is_zero) {
+                    return Err(PolishError::DivisionByZero {
+                        operator: Some(op.to_string()),
+                        span: expr.span,
+                    });
+                }
+            }
+        }
+        let result = operator
+            .apply(&taken)
+            .map_err(|e| e.at_operator(op, expr.span))?;
+        let result = self.check(result, Some(op), expr.span)?;
+        observer.on_apply(op, &taken, result.clone());
+        Ok(result)
+    }
+
+    // 厳密モードでは有限の値だけを通す
+    fn check(
+        &self,
+        value: N,
+        operator: Option<&str>,
+        span: Option<Span>,
+    ) -> Result<N, PolishError> {
+        if self.mode == Mode::Permissive || value.is_finite() {
+            return Ok(value);
+        }
+        let operator = operator.map(str::to_string);
+        if value.is_nan() {
+            Err(PolishError::NotANumber { operator, span })
+        } else {
+            Err(PolishError::Overflow { operator, span })
+        }
+    }
+}
+
+// `Evaluator::visit` でこれから辿る節
+enum Visit<'e, N> {
+    // 数値・変数を読むか、演算子の被演算子を積む
+    Enter(&'e Expr<N>),
+    // 被演算子を辿り終えた演算子を、被演算子の数とともに適用する
+    Apply(&'e Expr<N>, &'e str, usize),
+}
+
+impl<N: Number> Default for Evaluator<'_, N> {
+    fn default() -> Self {
+        Evaluator {
+            registry: default_registry(),
+            env: None,
+            mode: Mode::Permissive,
+            notation: Notation::Prefix,
+        }
+    }
+}
+
+// 参照しか持たないので、N が Copy でなくてもコピーできる
+impl<N> Clone for Evaluator<'_, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N> Copy for Evaluator<'_, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, pn};
+
+    #[test]
+    fn permissive_test() {
+        assert_eq!(pn("/ 1 0"), Ok(f64::INFINITY));
+        assert_eq!(pn("/ -1 0"), Ok(f64::NEG_INFINITY));
+        assert!(pn("% 1 0").unwrap().is_nan());
+        assert_eq!(Evaluator::new().pn("* 1e308 10"), Ok(f64::INFINITY));
+    }
+
+    #[test]
+    fn strict_test() {
+        let strict = Evaluator::new().mode(Mode::Strict);
+        let division_by_zero = |operator: &str, start, end, index| {
+            Err(PolishError::DivisionByZero {
+                operator: Some(operator.to_string()),
+                span: Some(Span::new(start, end, index)),
+            })
+        };
+        let exoressions = [
+            ("+ 1 2", Ok(3.0)),
+            ("/ 0 5", Ok(0.0)),
+            ("sqrt 0", Ok(0.0)),
+            ("/ 1 0", division_by_zero("/", 0, 1, 0)),
+            ("% 1 0", division_by_zero("%", 0, 1, 0)),
+            ("+ 1 // 1 -0", division_by_zero("//", 4, 6, 2)),
+            ("mod 1 - 2 2", division_by_zero("mod", 0, 3, 0)),
+            (
+                "* 1e308 10",
+                Err(PolishError::Overflow {
+                    operator: Some("*".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "ln 0",
+                Err(PolishError::Overflow {
+                    operator: Some("ln".to_string()),
+                    span: Some(Span::new(0, 2, 0)),
+                }),
+            ),
+            (
+                "+ 1 sqrt -1",
+                Err(PolishError::NotANumber {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(4, 8, 2)),
+                }),
+            ),
+            (
+                "+ 1 inf",
+                Err(PolishError::Overflow {
+                    operator: None,
+                    span: Some(Span::new(4, 7, 2)),
+                }),
+            ),
+            (
+                "nan",
+                Err(PolishError::NotANumber {
+                    operator: None,
+                    span: Some(Span::new(0, 3, 0)),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(strict.pn(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn strict_variable_test() {
+        let mut env = Environment::new();
+        env.set("x", f64::NAN).set("y", 0.0);
+        let strict = Evaluator::new().env(&env).mode(Mode::Strict);
+        assert_eq!(
+            strict.eval(&parse("+ 1 x").unwrap()),
+            Err(PolishError::NotANumber {
+                operator: None,
+                span: Some(Span::new(4, 5, 2)),
+            })
+        );
+        assert!(matches!(
+            strict.eval(&parse("/ 1 y").unwrap()),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+        assert_eq!(
+            Evaluator::new().env(&env).eval(&parse("/ 1 y").unwrap()),
+            Ok(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn divisor_test() {
+        let mut registry = OperatorRegistry::new();
+        registry
+            .register_binary("ratio", |a, b| a / b)
+            .set_divisor("ratio", 1);
+        let strict = Evaluator::new().registry(&registry).mode(Mode::Strict);
+        assert_eq!(strict.pn("ratio 1 4"), Ok(0.25));
+        assert!(matches!(
+            strict.pn("ratio 1 0"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+    }
+}
@@ -0,0 +1,791 @@
+use crate::registry::default_registry;
+use crate::{
+    folds, is_identifier, render_with, Expr, ExprKind, Number, Operator, OperatorRegistry, Piece,
+    PolishError, Span, Token,
+};
+use regex::Regex;
+use std::sync::OnceLock;
+
+// 中置記法の数値リテラル。符号は単項演算子として読むので含めない
+const INFIX_NUMBER_PATTERN: &str = r"^(?:[0-9](?:_?[0-9])*(?:\.(?:[0-9](?:_?[0-9])*)?)?|\.[0-9](?:_?[0-9])*)(?:[eE][+-]?[0-9](?:_?[0-9])*)?";
+
+const WORD_PATTERN: &str = r"^[A-Za-z_][A-Za-z0-9_]*";
+
+fn infix_number_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(INFIX_NUMBER_PATTERN).unwrap())
+}
+
+// `Number::scan_literal` の既定の実装。先頭の数値リテラルの長さを返す
+pub(crate) fn scan_number(text: &str) -> Option<usize> {
+    infix_number_regex().find(text).map(|m| m.end())
+}
+
+fn word_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(WORD_PATTERN).unwrap())
+}
+
+// 単項の `-` は neg として読み、`^` より弱く `*` より強く結び付く
+const NEG_PRECEDENCE: u8 = 3;
+
+// 中置記法の二項演算子の優先順位と、右結合かどうか
+pub(crate) fn binary_operator(symbol: &str) -> Option<(u8, bool)> {
+    match symbol {
+        "+" | "-" => Some((1, false)),
+        "*" | "/" | "%" | "//" => Some((2, false)),
+        "^" => Some((4, true)),
+        _ => None,
+    }
+}
+
+// 空白がなくても区切れるように、数値・名前・記号を 1 文字ずつ読む
+fn tokenize<N: Number>(expression: &str) -> Result<Vec<Token<'_>>, PolishError> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    while let Some(c) = expression[start..].chars().next() {
+        if c.is_whitespace() {
+            start += c.len_utf8();
+            continue;
+        }
+        let rest = &expression[start..];
+        let len = if let Some(len) = N::scan_literal(rest) {
+            len
+        } else if let Some(m) = word_regex().find(rest) {
+            m.end()
+        } else if rest.starts_with("//") {
+            2
+        } else if "+-*/%^(),".contains(c) {
+            1
+        } else {
+            return Err(PolishError::UseUnavailableCharacter {
+                token: c.to_string(),
+                span: Span::new(start, start + c.len_utf8(), tokens.len()),
+            });
+        };
+        tokens.push(Token {
+            text: &rest[..len],
+            span: Span::new(start, start + len, tokens.len()),
+        });
+        start += len;
+    }
+    Ok(tokens)
+}
+
+// 出力に積む前の演算子と括弧
+enum Pending {
+    Operator {
+        name: String,
+        arity: usize,
+        precedence: u8,
+        span: Span,
+    },
+    Paren {
+        span: Span,
+        // 関数呼び出しの括弧なら、関数名・引数の数・可変長かどうか・関数名の位置
+        function: Option<(String, usize, bool, Span)>,
+        args: usize,
+    },
+}
+
+fn reduce<N>(
+    output: &mut Vec<Expr<N>>,
+    op: String,
+    arity: usize,
+    span: Span,
+) -> Result<(), PolishError> {
+    if output.len() < arity {
+        return Err(PolishError::NotEnoughOperands {
+            operator: op,
+            expected: arity,
+            found: output.len(),
+            span: Some(span),
+        });
+    }
+    let children = output.split_off(output.len() - arity);
+    output.push(Expr {
+        kind: ExprKind::Operator { op, children },
+        span: Some(span),
+    });
+    Ok(())
+}
+
+// `)` を読んだので、対応する `(` までの演算子を出力に移す
+fn close_paren<N>(
+    output: &mut Vec<Expr<N>>,
+    pending: &mut Vec<Pending>,
+    token: &Token,
+    args: usize,
+) -> Result<(), PolishError> {
+    loop {
+        match pending.pop() {
+            Some(Pending::Operator {
+                name, arity, span, ..
+            }) => reduce(output, name, arity, span)?,
+            Some(Pending::Paren {
+                function, args: n, ..
+            }) => {
+                let Some((name, arity, variadic, span)) = function else {
+                    return Ok(());
+                };
+                let found = n + args;
+                if found > arity && variadic {
+                    return reduce(output, name, found, span);
+                }
+                if found > arity {
+                    return Err(PolishError::TooManyOperands {
+                        remaining: found - arity,
+                        span: Some(span),
+                    });
+                }
+                if found < arity {
+                    return Err(PolishError::NotEnoughOperands {
+                        operator: name,
+                        expected: arity,
+                        found,
+                        span: Some(span),
+                    });
+                }
+                return reduce(output, name, arity, span);
+            }
+            None => return Err(PolishError::UnbalancedParenthesis { span: token.span }),
+        }
+    }
+}
+
+fn lookup<N: Number>(
+    registry: &OperatorRegistry<N>,
+    name: &str,
+    span: Span,
+) -> Result<usize, PolishError> {
+    registry
+        .arity(name)
+        .ok_or_else(|| PolishError::UnknownOperator {
+            operator: name.to_string(),
+            span: Some(span),
+        })
+}
+
+fn unexpected(token: &Token) -> PolishError {
+    PolishError::UnexpectedToken {
+        token: token.text.to_string(),
+        span: token.span,
+    }
+}
+
+/// receive an exoression in infix notation as `&str`, then `from_infix` return the same tree as `parse` does,
+/// as `Expr` or `PolishError`.
+///
+/// the `Expr` prints as Polish notation, so `to_string` gives an exoression `pn` can read.
+///
+/// - `+ - * / % // ^` are binary operators, `^` is right-associative and binds tightest,
+///   then `* / % //`, then `+ -`.
+/// - a leading `-` is `neg`, so `-2 ^ 2` is `neg ^ 2 2`. a leading `+` does nothing.
+/// - any other operator is called like a function: `sqrt(16)`, `max(1, 2)`, `clamp(x, 0, 1)`.
+///   a variadic operator takes any number of arguments: `max(1, 2, 3)`.
+/// - other words are variables, and `(` `)` group as usual.
+///
+/// a parenthesis without a pair is `UnbalancedParenthesis`,
+/// and a token in the wrong place, such as the `*` of `1 + * 2`, is `UnexpectedToken`.
+/// spans point into the infix exoression.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::from_infix;
+///
+/// let expr = from_infix("(5 + 1) * 2").unwrap();
+/// assert_eq!(expr.to_string(), "* + 5 1 2");
+/// assert_eq!(expr.eval(), Ok(12.0));
+///
+/// let prefix = from_infix("max(2 ^ 3, -x)").unwrap().to_string();
+/// assert_eq!(prefix, "max ^ 2 3 neg x");
+/// ```
+pub fn from_infix(expression: &str) -> Result<Expr, PolishError> {
+    from_infix_with(default_registry(), expression)
+}
+
+/// same as `from_infix`, but operators and functions are looked up in `registry`.
+pub fn from_infix_with(registry: &OperatorRegistry, expression: &str) -> Result<Expr, PolishError> {
+    parse_infix(registry, expression)
+}
+
+pub(crate) fn parse_infix<N: Number>(
+    registry: &OperatorRegistry<N>,
+    expression: &str,
+) -> Result<Expr<N>, PolishError> {
+    let tokens = tokenize::<N>(expression)?;
+    if tokens.is_empty() {
+        return Err(PolishError::NotEnteredExoression);
+    }
+
+    let mut output: Vec<Expr<N>> = vec![];
+    let mut pending: Vec<Pending> = vec![];
+    // 次に被演算子が来るか、二項演算子か `)` か `,` が来るか
+    let mut expect_operand = true;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let text = token.text;
+        if expect_operand {
+            match text {
+                "(" => match negative_literal(&tokens, i) {
+                    Some(expr) => {
+                        output.push(expr);
+                        expect_operand = false;
+                        i += 3;
+                    }
+                    None => pending.push(Pending::Paren {
+                        span: token.span,
+                        function: None,
+                        args: 0,
+                    }),
+                },
+                "-" => pending.push(Pending::Operator {
+                    name: "neg".to_string(),
+                    arity: lookup(registry, "neg", token.span)?,
+                    precedence: NEG_PRECEDENCE,
+                    span: token.span,
+                }),
+                "+" => {}
+                // 引数のない関数呼び出し `f()`
+                ")" if matches!(
+                    pending.last(),
+                    Some(Pending::Paren {
+                        function: Some(_),
+                        args: 0,
+                        ..
+                    })
+                ) =>
+                {
+                    close_paren(&mut output, &mut pending, token, 0)?;
+                    expect_operand = false;
+                }
+                // 数値リテラルとして区切ったなら、その型の数値リテラルのはず
+                _ if N::scan_literal(text).is_some() || N::parse_literal(text).is_some() => {
+                    let value = N::parse_literal(text).ok_or_else(|| {
+                        PolishError::UseUnavailableCharacter {
+                            token: text.to_string(),
+                            span: token.span,
+                        }
+                    })?;
+                    output.push(Expr {
+                        kind: ExprKind::Number(value),
+                        span: Some(token.span),
+                    });
+                    expect_operand = false;
+                }
+                _ if is_identifier(text) => {
+                    match tokens.get(i + 1) {
+                        Some(next) if next.text == "(" => {
+                            let arity = lookup(registry, text, token.span)?;
+                            let variadic = registry.get(text).is_some_and(Operator::is_variadic);
+                            pending.push(Pending::Paren {
+                                span: next.span,
+                                function: Some((text.to_string(), arity, variadic, token.span)),
+                                args: 0,
+                            });
+                            i += 1;
+                        }
+                        _ => {
+                            // 演算子の名前は変数にできないので、引数がないものとする
+                            if let Some(arity) = registry.arity(text) {
+                                return Err(PolishError::NotEnoughOperands {
+                                    operator: text.to_string(),
+                                    expected: arity,
+                                    found: 0,
+                                    span: Some(token.span),
+                                });
+                            }
+                            output.push(Expr {
+                                kind: ExprKind::Variable(text.to_string()),
+                                span: Some(token.span),
+                            });
+                            expect_operand = false;
+                        }
+                    }
+                }
+                _ => return Err(unexpected(token)),
+            }
+        } else {
+            match text {
+                ")" => close_paren(&mut output, &mut pending, token, 1)?,
+                "," => loop {
+                    match pending.last_mut() {
+                        Some(Pending::Operator { .. }) => {
+                            if let Some(Pending::Operator {
+                                name, arity, span, ..
+                            }) = pending.pop()
+                            {
+                                reduce(&mut output, name, arity, span)?;
+                            }
+                        }
+                        Some(Pending::Paren {
+                            function: Some(_),
+                            args,
+                            ..
+                        }) => {
+                            *args += 1;
+                            expect_operand = true;
+                            break;
+                        }
+                        _ => return Err(unexpected(token)),
+                    }
+                },
+                _ => {
+                    let Some((precedence, right)) = binary_operator(text) else {
+                        return Err(unexpected(token));
+                    };
+                    let arity = lookup(registry, text, token.span)?;
+                    // 結び付きが強い演算子を先に出力に移す
+                    while let Some(Pending::Operator {
+                        precedence: top, ..
+                    }) = pending.last()
+                    {
+                        if *top < precedence || (*top == precedence && right) {
+                            break;
+                        }
+                        if let Some(Pending::Operator {
+                            name, arity, span, ..
+                        }) = pending.pop()
+                        {
+                            reduce(&mut output, name, arity, span)?;
+                        }
+                    }
+                    pending.push(Pending::Operator {
+                        name: text.to_string(),
+                        arity,
+                        precedence,
+                        span: token.span,
+                    });
+                    expect_operand = true;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    if expect_operand {
+        // 最後の演算子の被演算子が足りない
+        return Err(match pending.pop() {
+            Some(Pending::Operator {
+                name, arity, span, ..
+            }) => PolishError::NotEnoughOperands {
+                operator: name,
+                expected: arity,
+                found: arity.saturating_sub(1),
+                span: Some(span),
+            },
+            Some(Pending::Paren { span, .. }) => PolishError::UnbalancedParenthesis { span },
+            None => {
+                let last = &tokens[tokens.len() - 1];
+                PolishError::NotEnoughOperands {
+                    operator: last.text.to_string(),
+                    expected: 1,
+                    found: 0,
+                    span: Some(last.span),
+                }
+            }
+        });
+    }
+    while let Some(top) = pending.pop() {
+        match top {
+            Pending::Operator {
+                name, arity, span, ..
+            } => reduce(&mut output, name, arity, span)?,
+            Pending::Paren { span, .. } => {
+                return Err(PolishError::UnbalancedParenthesis { span });
+            }
+        }
+    }
+    Ok(output.remove(0))
+}
+
+// `(-5)` のように括弧ですぐ囲んだ負の数は、`neg 5` ではなく負の数のリテラルとして読む
+fn negative_literal<N: Number>(tokens: &[Token], i: usize) -> Option<Expr<N>> {
+    let [open, minus, number, close] = tokens.get(i..i + 4)? else {
+        return None;
+    };
+    if open.text != "("
+        || minus.text != "-"
+        || close.text != ")"
+        || minus.span.end != number.span.start
+    {
+        return None;
+    }
+    let value = N::parse_literal(&format!("-{}", number.text))?;
+    Some(Expr {
+        kind: ExprKind::Number(value),
+        span: Some(Span::new(
+            minus.span.start,
+            number.span.end,
+            minus.span.index,
+        )),
+    })
+}
+
+// 中置記法で書いたときの結び付きの強さ。数値・変数・関数呼び出しは括弧がいらない
+fn precedence<N: Number>(expr: &Expr<N>) -> u8 {
+    match &expr.kind {
+        // `2+3i` や `1/2` のように演算子を含むリテラルは、その演算子と同じ強さになる。
+        // 負の数は `(-5)` と括弧で囲んで書く
+        ExprKind::Number(n) => match n.literal_operator() {
+            Some("neg") => u8::MAX,
+            Some(op) => binary_operator(op).map_or(u8::MAX, |(precedence, _)| precedence),
+            None => u8::MAX,
+        },
+        ExprKind::Operator { op, children } => match (op.as_str(), children.len()) {
+            ("neg", 1) => NEG_PRECEDENCE,
+            (op, n) if n == 2 || folds::<N>(op, n) => {
+                binary_operator(op).map_or(u8::MAX, |(precedence, _)| precedence)
+            }
+            _ => u8::MAX,
+        },
+        _ => u8::MAX,
+    }
+}
+
+fn wrapped<'e, N: Number>(child: &'e Expr<N>, paren: bool, pieces: &mut Vec<Piece<'e, N>>) {
+    // 括弧で囲んだ `-2` は負の数のリテラルになるので、`neg 2` は `(- 2)` と書く
+    if let ExprKind::Operator { op, children } = &child.kind {
+        if let (
+            "neg",
+            [number @ Expr {
+                kind: ExprKind::Number(_),
+                ..
+            }],
+        ) = (op.as_str(), children.as_slice())
+        {
+            if paren {
+                pieces.push(Piece::Text("(- ".to_string()));
+                pieces.push(Piece::Expr(number));
+                pieces.push(Piece::Text(")".to_string()));
+                return;
+            }
+        }
+    }
+    if paren {
+        pieces.push(Piece::Text("(".to_string()));
+        pieces.push(Piece::Expr(child));
+        pieces.push(Piece::Text(")".to_string()));
+    } else {
+        pieces.push(Piece::Expr(child));
+    }
+}
+
+fn write_infix<N: Number>(expr: &Expr<N>) -> String {
+    render_with(expr, |expr, pieces| {
+        let ExprKind::Operator { op, children } = &expr.kind else {
+            match &expr.kind {
+                ExprKind::Number(n) if n.literal_operator() == Some("neg") => {
+                    pieces.push(Piece::Text(format!("({})", expr.token())))
+                }
+                _ => pieces.push(Piece::Text(expr.token())),
+            }
+            return;
+        };
+        match (op.as_str(), children.as_slice()) {
+            ("neg", [child]) => {
+                pieces.push(Piece::Minus);
+                wrapped(child, precedence(child) < NEG_PRECEDENCE, pieces);
+            }
+            // 可変長の `+` や `*` は `1 + 2 + 3` のように左から並べる
+            (op, [left, rest @ ..])
+                if binary_operator(op).is_some()
+                    && (rest.len() == 1 || folds::<N>(op, children.len())) =>
+            {
+                let (p, right_assoc) = binary_operator(op).unwrap_or_default();
+                let l = precedence(left);
+                // 同じ強さなら、結合しない側に括弧を付ける
+                wrapped(left, l < p || (l == p && right_assoc), pieces);
+                for right in rest {
+                    let r = precedence(right);
+                    // `+` と `*` は結合的なので、右側が同じ演算子なら括弧はいらない
+                    let associative = matches!(op, "+" | "*")
+                        && matches!(&right.kind, ExprKind::Operator { op: r_op, .. } if r_op == op);
+                    pieces.push(Piece::Text(format!(" {} ", op)));
+                    // 右側の単項の `-` は括弧がなくても読める
+                    wrapped(
+                        right,
+                        (r < p && r != NEG_PRECEDENCE) || (r == p && !right_assoc && !associative),
+                        pieces,
+                    );
+                }
+            }
+            (op, _) => {
+                pieces.push(Piece::Text(format!("{}(", op)));
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        pieces.push(Piece::Text(", ".to_string()));
+                    }
+                    pieces.push(Piece::Expr(child));
+                }
+                pieces.push(Piece::Text(")".to_string()));
+            }
+        }
+    })
+}
+
+impl<N: Number> Expr<N> {
+    /// write this tree in infix notation, with only the parentheses that are needed.
+    ///
+    /// `- / %` and `//` are left-associative, so a right operand of the same precedence keeps its parentheses,
+    /// and `^` is right-associative, so a left operand does.
+    /// `+` and `*` are associative, so `+ 1 + 2 3` is written as `1 + 2 + 3`,
+    /// which `from_infix` reads back as `+ + 1 2 3`. the value is the same, except for the rounding of floats.
+    /// `neg` is written as a leading `-`, and other operators are written as function calls.
+    ///
+    /// a negative number is written as `(-5)`, which `from_infix` reads back as the number `-5`,
+    /// while `-5` without the parentheses is `neg 5`.
+    ///
+    /// ## example
+    ///
+    /// ```
+    /// use polish_notation::parse;
+    ///
+    /// assert_eq!(parse("* + 5 1 2").unwrap().to_infix(), "(5 + 1) * 2");
+    /// assert_eq!(parse("- 5 - 1 2").unwrap().to_infix(), "5 - (1 - 2)");
+    /// assert_eq!(parse("^ ^ 2 3 2").unwrap().to_infix(), "(2 ^ 3) ^ 2");
+    /// assert_eq!(parse("max neg x sqrt 2").unwrap().to_infix(), "max(-x, sqrt(2))");
+    /// ```
+    pub fn to_infix(&self) -> String {
+        write_infix(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert, parse, pn, Environment, Notation};
+
+    #[test]
+    fn from_infix_test() {
+        let exoressions = [
+            ("(5 + 1) * 2", "* + 5 1 2"),
+            ("5 + 1 * 2", "+ 5 * 1 2"),
+            ("1 - 2 - 3", "- - 1 2 3"),
+            ("1 - (2 - 3)", "- 1 - 2 3"),
+            ("2 ^ 3 ^ 2", "^ 2 ^ 3 2"),
+            ("(2 ^ 3) ^ 2", "^ ^ 2 3 2"),
+            ("8 / 4 // 2 % 3", "% // / 8 4 2 3"),
+            ("-2 ^ 2", "neg ^ 2 2"),
+            ("2 ^ -1", "^ 2 neg 1"),
+            ("- -x * 3", "* neg neg x 3"),
+            ("+5 - +1", "- 5 1"),
+            ("2*(3+4)", "* 2 + 3 4"),
+            ("1_000.5e1", "10005"),
+            ("sqrt(16) + abs(-3)", "+ sqrt 16 abs neg 3"),
+            ("max(1, min(2, 3)) * 2", "* max 1 min 2 3 2"),
+            ("clamp(x + 1, 0, 1)", "clamp + x 1 0 1"),
+            ("fma(2, 3, 4) ^ 0.5", "^ fma 2 3 4 0.5"),
+            ("((1))", "1"),
+            ("inf - x", "- inf x"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let expr = from_infix(exoression.0).unwrap();
+            assert_eq!(expr.to_string(), exoression.1);
+            assert_eq!(parse(exoression.1).unwrap(), expr);
+        }
+
+        let mut env = Environment::new();
+        env.set("x", 0.5);
+        let expr = from_infix("(x + 1) * 4 - 2 ^ 3").unwrap();
+        assert_eq!(expr.eval_in(&env), Ok(-2.0));
+        assert_eq!(pn(&from_infix("10 - 4 - 3").unwrap().to_string()), Ok(3.0));
+    }
+
+    #[test]
+    fn error_test() {
+        let exoressions = [
+            (
+                "(1 + 2",
+                PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                },
+            ),
+            (
+                "1 + 2)",
+                PolishError::UnbalancedParenthesis {
+                    span: Span::new(5, 6, 3),
+                },
+            ),
+            (
+                "max(1, 2",
+                PolishError::UnbalancedParenthesis {
+                    span: Span::new(3, 4, 1),
+                },
+            ),
+            (
+                "1 + * 2",
+                PolishError::UnexpectedToken {
+                    token: "*".to_string(),
+                    span: Span::new(4, 5, 2),
+                },
+            ),
+            (
+                "1 2",
+                PolishError::UnexpectedToken {
+                    token: "2".to_string(),
+                    span: Span::new(2, 3, 1),
+                },
+            ),
+            (
+                "(1, 2)",
+                PolishError::UnexpectedToken {
+                    token: ",".to_string(),
+                    span: Span::new(2, 3, 2),
+                },
+            ),
+            (
+                "()",
+                PolishError::UnexpectedToken {
+                    token: ")".to_string(),
+                    span: Span::new(1, 2, 1),
+                },
+            ),
+            (
+                "1 +",
+                PolishError::NotEnoughOperands {
+                    operator: "+".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(2, 3, 1)),
+                },
+            ),
+            (
+                "max(1)",
+                PolishError::NotEnoughOperands {
+                    operator: "max".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(0, 3, 0)),
+                },
+            ),
+            (
+                "sqrt(1, 2)",
+                PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(0, 4, 0)),
+                },
+            ),
+            (
+                "sqrt + 1",
+                PolishError::NotEnoughOperands {
+                    operator: "sqrt".to_string(),
+                    expected: 1,
+                    found: 0,
+                    span: Some(Span::new(0, 4, 0)),
+                },
+            ),
+            (
+                "hypot(3, 4)",
+                PolishError::UnknownOperator {
+                    operator: "hypot".to_string(),
+                    span: Some(Span::new(0, 5, 0)),
+                },
+            ),
+            (
+                "1 + $",
+                PolishError::UseUnavailableCharacter {
+                    token: "$".to_string(),
+                    span: Span::new(4, 5, 2),
+                },
+            ),
+            ("  ", PolishError::NotEnteredExoression),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(from_infix(exoression.0), Err(exoression.1));
+        }
+    }
+
+    #[test]
+    fn to_infix_test() {
+        let exoressions = [
+            ("* + 5 1 2", "(5 + 1) * 2"),
+            ("+ 5 * 1 2", "5 + 1 * 2"),
+            ("- - 1 2 3", "1 - 2 - 3"),
+            ("- 1 - 2 3", "1 - (2 - 3)"),
+            ("- 1 + 2 3", "1 - (2 + 3)"),
+            ("+ + 1 2 3", "1 + 2 + 3"),
+            ("* * a b c", "a * b * c"),
+            ("+ 1 - 2 3", "1 + (2 - 3)"),
+            ("/ a * b c", "a / (b * c)"),
+            ("* a / b c", "a * (b / c)"),
+            ("% % a b c", "a % b % c"),
+            ("% a % b c", "a % (b % c)"),
+            ("// a // b c", "a // (b // c)"),
+            ("^ 2 ^ 3 2", "2 ^ 3 ^ 2"),
+            ("^ ^ 2 3 2", "(2 ^ 3) ^ 2"),
+            ("^ * 2 3 + 1 x", "(2 * 3) ^ (1 + x)"),
+            ("neg ^ 2 2", "-2 ^ 2"),
+            ("^ neg 2 2", "(- 2) ^ 2"),
+            ("^ -2 2", "(-2) ^ 2"),
+            ("- 1 -2", "1 - (-2)"),
+            ("max -1 neg 1", "max((-1), -1)"),
+            ("-1", "(-1)"),
+            ("^ 2 neg 1", "2 ^ -1"),
+            ("neg + 1 2", "-(1 + 2)"),
+            ("neg neg x", "- -x"),
+            ("* neg x 3", "-x * 3"),
+            ("- a neg b", "a - -b"),
+            ("sqrt + 1 x", "sqrt(1 + x)"),
+            ("clamp * x 2 0 1", "clamp(x * 2, 0, 1)"),
+            ("* 2 max 1 mod 7 3", "2 * max(1, mod(7, 3))"),
+            ("1.5", "1.5"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let expr = parse(exoression.0).unwrap();
+            assert_eq!(expr.to_infix(), exoression.1);
+            assert_eq!(from_infix(exoression.1).unwrap(), expr);
+        }
+
+        // 負の数は中置記法を経ても負の数のまま
+        let infix = convert("* -1 - x -2.5", Notation::Prefix, Notation::Infix).unwrap();
+        assert_eq!(infix, "(-1) * (x - (-2.5))");
+        assert_eq!(
+            convert(&infix, Notation::Infix, Notation::Prefix),
+            Ok("* -1 - x -2.5".to_string())
+        );
+        assert_eq!(from_infix("-1").unwrap().to_string(), "neg 1");
+        assert_eq!(from_infix("(- 1)").unwrap().to_string(), "neg 1");
+
+        // `+` と `*` の右側の括弧は省くので、左から結合した木として読み戻される
+        let exoressions = [
+            ("+ 1 + 2 3", "1 + 2 + 3", "+ + 1 2 3"),
+            ("* 2 * 3 4", "2 * 3 * 4", "* * 2 3 4"),
+            ("+ 1 + 2 + 3 4", "1 + 2 + 3 + 4", "+ + + 1 2 3 4"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let expr = parse(exoression.0).unwrap();
+            assert_eq!(expr.to_infix(), exoression.1);
+            let back = from_infix(exoression.1).unwrap();
+            assert_eq!(back.to_string(), exoression.2);
+            assert_eq!(back.eval(), expr.eval());
+        }
+
+        let expr = Expr::operator("+", vec![Expr::number(1.0)]);
+        assert_eq!(expr.to_infix(), "+(1)");
+    }
+
+    #[test]
+    fn registry_test() {
+        let mut registry = OperatorRegistry::empty();
+        registry
+            .register_binary("+", |a, b| a + b)
+            .register_binary("hypot", f64::hypot);
+        let expr = from_infix_with(&registry, "hypot(3, 4) + 1").unwrap();
+        assert_eq!(expr.to_string(), "+ hypot 3 4 1");
+        assert_eq!(
+            from_infix_with(&registry, "-1"),
+            Err(PolishError::UnknownOperator {
+                operator: "neg".to_string(),
+                span: Some(Span::new(0, 1, 0)),
+            })
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{Number, OperatorRegistry, PolishError, Rational};
+use std::fmt;
+
+/// A closed interval `[lo, hi]` of `f64` that is sure to contain the exact value.
+///
+/// every operator rounds the lower bound down and the upper bound up,
+/// so the result of an exoression encloses the exact result for every value of its operands.
+/// number literals are read as the smallest interval that contains the decimal value,
+/// so `0.1` is not the single `f64` next to 0.1 but the two `f64` around it.
+/// an interval literal is written `[lo,hi]` without spaces.
+///
+/// a divisor interval that contains zero fails with `DivisionByZero` in `Mode::Strict`,
+/// and gives an unbounded interval in `Mode::Permissive`, such as `[1,inf]` for `/ 1 [0,1]`.
+/// `^` needs an integer exponent, and there are no `ln`, `sin` or other functions
+/// whose rounding cannot be bounded.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, Evaluator, Interval, Mode, PolishError};
+///
+/// let ans = pn_as::<Interval>("* [1,2] - [3,4] [1,2]").unwrap();
+/// assert_eq!(ans, Interval::new(1.0, 6.0).unwrap());
+///
+/// let ans = pn_as::<Interval>("+ 0.1 0.2").unwrap();
+/// assert!(ans.lo() < 0.3 && 0.3 < ans.hi());
+///
+/// assert_eq!(pn_as::<Interval>("/ 1 [0,2]").unwrap().to_string(), "[0.5,inf]");
+/// assert!(matches!(
+///     Evaluator::<Interval>::default().mode(Mode::Strict).pn("/ 1 [0,2]"),
+///     Err(PolishError::DivisionByZero { .. })
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    /// `[lo, hi]`, or `None` when `lo` is above `hi` or either is NaN.
+    pub fn new(lo: f64, hi: f64) -> Option<Self> {
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+
+    /// the interval that contains only `x`.
+    pub fn point(x: f64) -> Self {
+        Interval { lo: x, hi: x }
+    }
+
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    /// `hi - lo`, rounded up.
+    pub fn width(&self) -> f64 {
+        sub_up(self.hi, self.lo)
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    // 実数全体
+    fn entire() -> Self {
+        Interval {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            (self.lo, other.lo),
+            (self.lo, other.hi),
+            (self.hi, other.lo),
+            (self.hi, other.hi),
+        ];
+        Interval {
+            lo: products
+                .iter()
+                .map(|&(a, b)| mul_round(a, b).0)
+                .fold(f64::INFINITY, f64::min),
+            hi: products
+                .iter()
+                .map(|&(a, b)| mul_round(a, b).1)
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    fn div(self, other: Self) -> Self {
+        let (a, b) = (self, other);
+        if b.contains(0.0) {
+            // 0 を含む区間で割ると、0 の側へ限りなく大きくなる
+            return if b.lo == 0.0 && b.hi == 0.0 || a.contains(0.0) {
+                Interval::entire()
+            } else if b.lo == 0.0 && a.lo > 0.0 {
+                Interval::new(div_round(a.lo, b.hi).0, f64::INFINITY).unwrap()
+            } else if b.lo == 0.0 {
+                Interval::new(f64::NEG_INFINITY, div_round(a.hi, b.hi).1).unwrap()
+            } else if b.hi == 0.0 && a.lo > 0.0 {
+                Interval::new(f64::NEG_INFINITY, div_round(a.lo, b.lo).1).unwrap()
+            } else if b.hi == 0.0 {
+                Interval::new(div_round(a.hi, b.lo).0, f64::INFINITY).unwrap()
+            } else {
+                Interval::entire()
+            };
+        }
+        let quotients = [(a.lo, b.lo), (a.lo, b.hi), (a.hi, b.lo), (a.hi, b.hi)];
+        Interval {
+            lo: quotients
+                .iter()
+                .map(|&(a, b)| div_round(a, b).0)
+                .fold(f64::INFINITY, f64::min),
+            hi: quotients
+                .iter()
+                .map(|&(a, b)| div_round(a, b).1)
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n < 0 {
+            return Interval::point(1.0).div(self.powi(-n));
+        }
+        let n = n.unsigned_abs();
+        if n % 2 == 1 {
+            // 奇数乗は単調に増える
+            return Interval {
+                lo: signed_pow(self.lo, n).0,
+                hi: signed_pow(self.hi, n).1,
+            };
+        }
+        // 偶数乗は絶対値の最小と最大で決まる
+        let (lo, hi) = (self.lo.abs(), self.hi.abs());
+        let min = if self.contains(0.0) { 0.0 } else { lo.min(hi) };
+        Interval {
+            lo: pow_round(min, n).0,
+            hi: pow_round(lo.max(hi), n).1,
+        }
+    }
+}
+
+// 誤差 err の符号を見て、計算結果 value を下と上に丸める。
+// err が NaN のときは、どちらに丸めるべきか分からないので両側に広げる
+fn round(value: f64, err: f64) -> (f64, f64) {
+    let lo = if err < 0.0 || err.is_nan() {
+        value.next_down()
+    } else {
+        value
+    };
+    let hi = if err > 0.0 || err.is_nan() {
+        value.next_up()
+    } else {
+        value
+    };
+    (lo, hi)
+}
+
+// TwoSum で a + b の丸め誤差を求める
+fn add_round(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    round(s, (a - (s - bb)) + (b - bb))
+}
+
+fn sub_up(a: f64, b: f64) -> f64 {
+    add_round(a, -b).1
+}
+
+// 0 に無限大を掛けても 0 とする
+fn mul_round(a: f64, b: f64) -> (f64, f64) {
+    if a == 0.0 || b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let p = a * b;
+    round(p, a.mul_add(b, -p))
+}
+
+// 余り a - q * b の符号から、a / b が q より大きいか小さいかが分かる
+fn div_round(a: f64, b: f64) -> (f64, f64) {
+    let q = a / b;
+    let r = (-q).mul_add(b, a);
+    round(q, if b < 0.0 { -r } else { r })
+}
+
+fn sqrt_round(x: f64) -> (f64, f64) {
+    let s = x.sqrt();
+    round(s, (-s).mul_add(s, x))
+}
+
+// 0 以上の x の n 乗を、下と上に丸めながら二乗を繰り返して求める
+fn pow_round(x: f64, mut n: u32) -> (f64, f64) {
+    let (mut base_lo, mut base_hi) = (x, x);
+    let (mut lo, mut hi) = (1.0, 1.0);
+    while n > 0 {
+        if n % 2 == 1 {
+            lo = mul_round(lo, base_lo).0;
+            hi = mul_round(hi, base_hi).1;
+        }
+        base_lo = mul_round(base_lo, base_lo).0;
+        base_hi = mul_round(base_hi, base_hi).1;
+        n /= 2;
+    }
+    (lo, hi)
+}
+
+// 負の数の奇数乗は、絶対値の奇数乗の符号を変えたもの
+fn signed_pow(x: f64, n: u32) -> (f64, f64) {
+    if x < 0.0 {
+        let (lo, hi) = pow_round(-x, n);
+        (-hi, -lo)
+    } else {
+        pow_round(x, n)
+    }
+}
+
+// 10 進のリテラルの値を挟む、いちばん近い 2 つの f64
+fn enclose(token: &str) -> Option<(f64, f64)> {
+    let x = f64::parse_literal(token)?;
+    if x.is_nan() {
+        return None;
+    }
+    let (Some(exact), Some(nearest)) = (Rational::parse_literal(token), Rational::from_f64(x))
+    else {
+        // 無限大や、正確な値が分からないものは両側に広げる
+        return Some((x.next_down(), x.next_up()));
+    };
+    Some(match nearest.cmp(&exact) {
+        std::cmp::Ordering::Less => (x, x.next_up()),
+        std::cmp::Ordering::Equal => (x, x),
+        std::cmp::Ordering::Greater => (x.next_down(), x),
+    })
+}
+
+/// prints `[lo,hi]`, which can be read back as an interval literal that contains this interval.
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{},{}]", self.lo, self.hi)
+    }
+}
+
+/// a number literal is the smallest interval that contains it, and `[lo,hi]` is an interval literal
+/// whose bounds are rounded outward. an interval "is zero" when it contains zero,
+/// so `Mode::Strict` rejects such a divisor.
+impl Number for Interval {
+    fn parse_literal(token: &str) -> Option<Self> {
+        let Some(bounds) = token.strip_prefix('[') else {
+            return enclose(token).map(|(lo, hi)| Interval { lo, hi });
+        };
+        let (lo, hi) = bounds.strip_suffix(']')?.split_once(',')?;
+        Interval::new(enclose(lo)?.0, enclose(hi)?.1)
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register_binary("+", |a, b| Interval {
+                lo: add_round(a.lo, b.lo).0,
+                hi: add_round(a.hi, b.hi).1,
+            })
+            .register_binary("-", |a, b| Interval {
+                lo: add_round(a.lo, -b.hi).0,
+                hi: add_round(a.hi, -b.lo).1,
+            })
+            .register_binary("*", Interval::mul)
+            .register_binary("/", Interval::div)
+            .register("^", 2, |x| {
+                let exp = x[1].lo;
+                if x[1].hi != exp || exp.fract() != 0.0 || exp.abs() > i32::MAX as f64 {
+                    return Err(PolishError::DomainError {
+                        operator: None,
+                        span: None,
+                    });
+                }
+                Ok(x[0].powi(exp as i32))
+            })
+            .register("sqrt", 1, |x| {
+                if x[0].hi < 0.0 {
+                    return Err(PolishError::DomainError {
+                        operator: None,
+                        span: None,
+                    });
+                }
+                // 負の部分は定義域の外なので除く
+                Ok(Interval {
+                    lo: sqrt_round(x[0].lo.max(0.0)).0,
+                    hi: sqrt_round(x[0].hi).1,
+                })
+            })
+            .register_binary("min", |a, b| Interval {
+                lo: a.lo.min(b.lo),
+                hi: a.hi.min(b.hi),
+            })
+            .register_binary("max", |a, b| Interval {
+                lo: a.lo.max(b.lo),
+                hi: a.hi.max(b.hi),
+            })
+            .register_unary("neg", |a| Interval {
+                lo: -a.hi,
+                hi: -a.lo,
+            })
+            .register_unary("abs", |a| {
+                if a.contains(0.0) {
+                    Interval {
+                        lo: 0.0,
+                        hi: a.hi.max(-a.lo),
+                    }
+                } else {
+                    Interval {
+                        lo: a.lo.abs().min(a.hi.abs()),
+                        hi: a.lo.abs().max(a.hi.abs()),
+                    }
+                }
+            })
+            .register_unary("floor", |a| Interval {
+                lo: a.lo.floor(),
+                hi: a.hi.floor(),
+            })
+            .register_unary("ceil", |a| Interval {
+                lo: a.lo.ceil(),
+                hi: a.hi.ceil(),
+            })
+            .set_divisor("/", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        self.contains(0.0)
+    }
+
+    fn is_finite(&self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    // `[1,2]` のような区間のリテラルは `]` までをひとつのトークンにする
+    fn scan_literal(text: &str) -> Option<usize> {
+        if text.starts_with('[') {
+            Some(text.find(']').map_or(text.len(), |i| i + 1))
+        } else {
+            f64::scan_literal(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn_as, Evaluator, Mode, Notation, Span};
+
+    #[test]
+    fn interval_test() {
+        let i = |lo, hi| Ok(Interval::new(lo, hi).unwrap());
+        let exoressions = [
+            ("+ [1,2] [3,4]", i(4.0, 6.0)),
+            ("- [1,2] [3,4]", i(-3.0, -1.0)),
+            ("* [-1,2] [3,4]", i(-4.0, 8.0)),
+            ("* [-2,-1] [-4,3]", i(-6.0, 8.0)),
+            ("/ [1,2] [4,8]", i(0.125, 0.5)),
+            ("/ [-1,2] [-4,-2]", i(-1.0, 0.5)),
+            ("^ [-1,2] 2", i(0.0, 4.0)),
+            ("^ [-2,1] 3", i(-8.0, 1.0)),
+            ("^ [2,4] -1", i(0.25, 0.5)),
+            ("^ [2,4] 0", i(1.0, 1.0)),
+            ("sqrt [4,9]", i(2.0, 3.0)),
+            ("sqrt [-4,9]", i(0.0, 3.0)),
+            ("abs [-3,2]", i(0.0, 3.0)),
+            ("neg [1,2]", i(-2.0, -1.0)),
+            ("min [1,5] [2,3]", i(1.0, 3.0)),
+            ("floor [1.5,2.5]", i(1.0, 2.0)),
+            // 0 を含む区間で割ると、限りのない区間になる
+            ("/ [1,2] [0,4]", i(0.25, f64::INFINITY)),
+            ("/ [1,2] [-4,0]", i(f64::NEG_INFINITY, -0.25)),
+            ("/ [-2,-1] [0,4]", i(f64::NEG_INFINITY, -0.25)),
+            ("/ 1 [-1,1]", i(f64::NEG_INFINITY, f64::INFINITY)),
+            ("/ [-1,1] [1,2]", i(-1.0, 1.0)),
+            ("* [0,1] [-inf,inf]", i(f64::NEG_INFINITY, f64::INFINITY)),
+            // 以下エラーテスト
+            (
+                "sqrt [-2,-1]",
+                Err(PolishError::DomainError {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "^ 2 [1,2]",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 [2,1]",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "[2,1]".to_string(),
+                    span: Span::new(4, 9, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn_as::<Interval>(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn rounding_test() {
+        // 0.1 は f64 で正確に表せないので、前後の f64 で挟む
+        let tenth = Interval::parse_literal("0.1").unwrap();
+        assert_eq!(tenth.hi(), 0.1);
+        assert_eq!(tenth.lo(), 0.1f64.next_down());
+        assert_eq!(Interval::parse_literal("0.5"), Some(Interval::point(0.5)));
+
+        let ans = pn_as::<Interval>("+ 0.1 0.2").unwrap();
+        assert!(ans.contains(0.1 + 0.2) && ans.lo() < 0.3);
+        let ans = pn_as::<Interval>("/ 1 3").unwrap();
+        assert_eq!(ans.hi(), (1.0f64 / 3.0).next_up());
+        assert_eq!(ans.lo(), 1.0 / 3.0);
+        let ans = pn_as::<Interval>("sqrt 2").unwrap();
+        assert_eq!(ans.width(), f64::EPSILON);
+        // 正確に計算できるものは広げない
+        assert_eq!(pn_as::<Interval>("* 1.5 4"), Ok(Interval::point(6.0)));
+        assert_eq!(
+            pn_as::<Interval>("* 1e300 1e300").map(|ans| ans.hi()),
+            Ok(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn mode_test() {
+        let strict = Evaluator::<Interval>::default().mode(Mode::Strict);
+        assert_eq!(
+            strict.pn("/ 1 [-1,1]"),
+            Err(PolishError::DivisionByZero {
+                operator: Some("/".to_string()),
+                span: Some(Span::new(0, 1, 0)),
+            })
+        );
+        assert_eq!(strict.pn("/ 1 [1,2]"), Ok(Interval::new(0.5, 1.0).unwrap()));
+        assert!(matches!(
+            strict.pn("* 1e300 1e300"),
+            Err(PolishError::Overflow { .. })
+        ));
+
+        let registry = OperatorRegistry::<Interval>::new();
+        let expr = Notation::Infix
+            .parse(&registry, "[1,2] * x - [0,1]")
+            .unwrap();
+        assert_eq!(expr.to_string(), "- * [1,2] x [0,1]");
+        assert_eq!(expr.to_infix(), "[1,2] * x - [0,1]");
+        assert!(matches!(
+            Notation::Infix.parse(&registry, "1 + [1,2"),
+            Err(PolishError::UseUnavailableCharacter { .. })
+        ));
+    }
+}
@@ -1,18 +1,104 @@
 use regex::Regex;
 use std::fmt;
+use std::sync::OnceLock;
+
+mod complex;
+mod decimal;
+mod diagnostic;
+mod environment;
+mod evaluator;
+mod infix;
+mod interval;
+mod measurement;
+mod notation;
+mod number;
+mod observer;
+mod quantity;
+mod rational;
+mod registry;
+mod sexpr;
+mod trace;
+
+pub use bigdecimal::BigDecimal;
+pub use decimal::{DecimalContext, Rounding};
+pub use diagnostic::Diagnostic;
+pub use environment::Environment;
+pub use evaluator::{Evaluator, Mode};
+pub use infix::{from_infix, from_infix_with};
+pub use interval::Interval;
+pub use measurement::Measurement;
+pub use notation::{convert, convert_with, parse_rpn, rpn, Notation};
+pub use num_bigint::BigInt;
+pub use num_complex::Complex64;
+pub use number::{pn_as, Number};
+pub use observer::{EvalObserver, TraceEvent, TraceRecorder};
+pub use quantity::Quantity;
+pub use rational::Rational;
+use registry::default_registry;
+pub use registry::{Operator, OperatorRegistry};
+pub use trace::{pn_trace, Step};
+
+/// Where a token is in the exoression.
+///
+/// `start` and `end` are byte offsets, so `&expression[span.start..span.end]` is the token text.
+/// `index` counts the tokens from 0 at the left of the exoression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub index: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, index: usize) -> Self {
+        Span { start, end, index }
+    }
+}
 
 /// Custom Error type
 /// 
 /// ## list Explanation
 /// 
 /// - FailedCalculate,
-///     - when the calculation failed because of something.
+///     - when the calculation failed because of something else. the built-in operators never return it,
+///       but your own operators can.
 /// - NotEnoughOperands,
-///     - when there are not enough operands.
+///     - when there are not enough operands. it holds the operator, how many operands it needs and how many it found.
 /// - UseUnavailableCharacter,
-///     - when you use unavailable character.
+///     - when you use unavailable character. it holds the token that contains it.
 /// - NotEnteredExoression,
 ///     - when you not entered exoression.
+/// - UndefinedVariable,
+///     - when a variable in the exoression is not set in the `Environment`. it holds the name of the variable.
+/// - UnknownOperator,
+///     - when an `Expr` uses an operator that is not in the `OperatorRegistry`. it holds the operator.
+/// - TooManyOperands,
+///     - when operands are left over, such as `1 2`. it holds how many are left over.
+/// - DivisionByZero,
+///     - when an operator divides by zero. it holds the operator.
+/// - DomainError,
+///     - when operands are outside the values an operator accepts, such as `clamp 1 3 0`. it holds the operator.
+/// - Overflow,
+///     - in `Mode::Strict`, when a value is infinite. it holds the operator that produced it.
+/// - NotANumber,
+///     - in `Mode::Strict`, when a value is NaN. it holds the operator that produced it.
+/// - UnbalancedParenthesis,
+///     - when a parenthesis in an infix exoression has no pair.
+/// - UnexpectedToken,
+///     - when a token of an infix exoression is in the wrong place, such as `1 + * 2`. it holds the token.
+/// - IntegerOverflow,
+///     - when the result of an operator does not fit in a fixed-width integer,
+///       such as `* 9223372036854775807 2` evaluated as `i64`. it holds the operator.
+/// - DimensionMismatch,
+///     - when the operands of an operator have dimensions it cannot take, such as `+ 1m 1s`
+///       evaluated as `Quantity`. it holds the expected and found units, and the operator.
+///
+/// the operator of `DivisionByZero`, `DomainError`, `Overflow`, `NotANumber`, `IntegerOverflow`
+/// and `DimensionMismatch` is `None`
+/// when the value is a number literal or a variable, or when an `Operator` is applied outside of an expression.
+/// every variant except `NotEnteredExoression` holds the `Span` of the token that caused it.
+/// the span is `None` only when the error comes from an `Expr` built by hand,
+/// or from an `Operator` applied outside of an expression.
 /// 
 /// # example
 /// 
@@ -27,76 +113,776 @@ use std::fmt;
 ///     Err(e) => eprintln!("{}", e),
 /// };
 /// ```
-#[derive(Debug, PartialEq)]
+///
+/// for end users, `PolishError::diagnostic` points at the token with a caret and gives a hint.
+///
+/// ```
+/// use polish_notation::pn;
+///
+/// let expression = "* + 5 1 - 7";
+/// if let Err(e) = pn(expression) {
+///     eprintln!("{}", e.diagnostic(expression));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub enum PolishError {
-    FailedCalculate,
-    NotEnoughOperands,
-    UseUnavailableCharacter,
+    FailedCalculate {
+        span: Option<Span>,
+    },
+    NotEnoughOperands {
+        operator: String,
+        expected: usize,
+        found: usize,
+        span: Option<Span>,
+    },
+    UseUnavailableCharacter {
+        token: String,
+        span: Span,
+    },
     NotEnteredExoression,
+    UndefinedVariable {
+        name: String,
+        span: Option<Span>,
+    },
+    UnknownOperator {
+        operator: String,
+        span: Option<Span>,
+    },
+    TooManyOperands {
+        remaining: usize,
+        span: Option<Span>,
+    },
+    DivisionByZero {
+        operator: Option<String>,
+        span: Option<Span>,
+    },
+    DomainError {
+        operator: Option<String>,
+        span: Option<Span>,
+    },
+    Overflow {
+        operator: Option<String>,
+        span: Option<Span>,
+    },
+    NotANumber {
+        operator: Option<String>,
+        span: Option<Span>,
+    },
+    UnbalancedParenthesis {
+        span: Span,
+    },
+    UnexpectedToken {
+        token: String,
+        span: Span,
+    },
+    IntegerOverflow {
+        operator: Option<String>,
+        span: Option<Span>,
+    },
+    DimensionMismatch {
+        expected: String,
+        found: String,
+        operator: Option<String>,
+        span: Option<Span>,
+    },
 }
+
+impl PolishError {
+    /// the location of the token that caused this error.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            PolishError::FailedCalculate { span }
+            | PolishError::NotEnoughOperands { span, .. }
+            | PolishError::UndefinedVariable { span, .. }
+            | PolishError::UnknownOperator { span, .. }
+            | PolishError::TooManyOperands { span, .. }
+            | PolishError::DivisionByZero { span, .. }
+            | PolishError::DomainError { span, .. }
+            | PolishError::Overflow { span, .. }
+            | PolishError::NotANumber { span, .. }
+            | PolishError::IntegerOverflow { span, .. }
+            | PolishError::DimensionMismatch { span, .. } => *span,
+            PolishError::UseUnavailableCharacter { span, .. }
+            | PolishError::UnbalancedParenthesis { span }
+            | PolishError::UnexpectedToken { span, .. } => Some(*span),
+            PolishError::NotEnteredExoression => None,
+        }
+    }
+
+    /// the error code `Diagnostic` shows, such as `E0002` for `NotEnoughOperands`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PolishError::FailedCalculate { .. } => "E0001",
+            PolishError::NotEnoughOperands { .. } => "E0002",
+            PolishError::UseUnavailableCharacter { .. } => "E0003",
+            PolishError::NotEnteredExoression => "E0004",
+            PolishError::UndefinedVariable { .. } => "E0005",
+            PolishError::UnknownOperator { .. } => "E0006",
+            PolishError::TooManyOperands { .. } => "E0007",
+            PolishError::DivisionByZero { .. } => "E0008",
+            PolishError::DomainError { .. } => "E0009",
+            PolishError::Overflow { .. } => "E0010",
+            PolishError::NotANumber { .. } => "E0011",
+            PolishError::UnbalancedParenthesis { .. } => "E0012",
+            PolishError::UnexpectedToken { .. } => "E0013",
+            PolishError::IntegerOverflow { .. } => "E0014",
+            PolishError::DimensionMismatch { .. } => "E0015",
+        }
+    }
+
+    /// render this error for `expression` with a caret under the token that caused it.
+    ///
+    /// `expression` must be the exoression this error came from.
+    pub fn diagnostic<'a>(&'a self, expression: &'a str) -> Diagnostic<'a> {
+        Diagnostic::new(self, expression)
+    }
+
+    // 位置を含まないエラーの説明
+    fn message(&self) -> String {
+        match self {
+            PolishError::FailedCalculate { .. } => "failed calculate".to_string(),
+            PolishError::NotEnoughOperands {
+                operator,
+                expected,
+                found,
+                ..
+            } => format!(
+                "not enough operands for `{}`: expected {}, found {}",
+                operator, expected, found
+            ),
+            PolishError::UseUnavailableCharacter { token, .. } => {
+                format!("use unavailable character in `{}`", token)
+            }
+            PolishError::NotEnteredExoression => "not entered exoression".to_string(),
+            PolishError::UndefinedVariable { name, .. } => format!("undefined variable `{}`", name),
+            PolishError::UnknownOperator { operator, .. } => {
+                format!("unknown operator `{}`", operator)
+            }
+            PolishError::TooManyOperands { remaining, .. } => {
+                format!("too many operands: {} left over", remaining)
+            }
+            PolishError::DivisionByZero { operator, .. } => match operator {
+                Some(operator) => format!("division by zero in `{}`", operator),
+                None => "division by zero".to_string(),
+            },
+            PolishError::DomainError { operator, .. } => match operator {
+                Some(operator) => format!("operands out of the domain of `{}`", operator),
+                None => "operands out of the domain".to_string(),
+            },
+            PolishError::Overflow { operator, .. } => match operator {
+                Some(operator) => format!("overflow to infinity in `{}`", operator),
+                None => "infinite value".to_string(),
+            },
+            PolishError::NotANumber { operator, .. } => match operator {
+                Some(operator) => format!("`{}` produced NaN", operator),
+                None => "NaN value".to_string(),
+            },
+            PolishError::UnbalancedParenthesis { .. } => "unbalanced parenthesis".to_string(),
+            PolishError::UnexpectedToken { token, .. } => format!("unexpected `{}`", token),
+            PolishError::IntegerOverflow { operator, .. } => match operator {
+                Some(operator) => format!("integer overflow in `{}`", operator),
+                None => "integer overflow".to_string(),
+            },
+            PolishError::DimensionMismatch {
+                expected,
+                found,
+                operator,
+                ..
+            } => match operator {
+                Some(operator) => format!(
+                    "mismatched dimensions in `{}`: expected `{}`, found `{}`",
+                    operator, expected, found
+                ),
+                None => format!(
+                    "mismatched dimensions: expected `{}`, found `{}`",
+                    expected, found
+                ),
+            },
+        }
+    }
+
+    // 演算子が返したエラーに、分からない演算子と位置を付ける
+    pub(crate) fn at_operator(mut self, name: &str, at: Option<Span>) -> Self {
+        match &mut self {
+            PolishError::DivisionByZero { operator, .. }
+            | PolishError::DomainError { operator, .. }
+            | PolishError::Overflow { operator, .. }
+            | PolishError::NotANumber { operator, .. }
+            | PolishError::IntegerOverflow { operator, .. }
+            | PolishError::DimensionMismatch { operator, .. }
+                if operator.is_none() =>
+            {
+                *operator = Some(name.to_string());
+            }
+            _ => {}
+        }
+        match &mut self {
+            PolishError::FailedCalculate { span }
+            | PolishError::NotEnoughOperands { span, .. }
+            | PolishError::UndefinedVariable { span, .. }
+            | PolishError::UnknownOperator { span, .. }
+            | PolishError::TooManyOperands { span, .. }
+            | PolishError::DivisionByZero { span, .. }
+            | PolishError::DomainError { span, .. }
+            | PolishError::Overflow { span, .. }
+            | PolishError::NotANumber { span, .. }
+            | PolishError::IntegerOverflow { span, .. }
+            | PolishError::DimensionMismatch { span, .. } => {
+                if span.is_none() {
+                    *span = at;
+                }
+            }
+            PolishError::UseUnavailableCharacter { .. }
+            | PolishError::NotEnteredExoression
+            | PolishError::UnbalancedParenthesis { .. }
+            | PolishError::UnexpectedToken { .. } => {}
+        }
+        self
+    }
+}
+
+impl std::error::Error for PolishError {}
+
 impl fmt::Display for PolishError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PolishError::FailedCalculate => write!(f, "failed calculate"),
-            PolishError::NotEnoughOperands => write!(f, "not enough operands"),
-            PolishError::UseUnavailableCharacter => write!(f, "use unavailable character"),
-            PolishError::NotEnteredExoression => write!(f, "not entered exoression"),
+        write!(f, "{}", self.message())?;
+        match self.span() {
+            Some(span) => write!(f, " at {}..{}", span.start, span.end),
+            None => Ok(()),
         }
     }
 }
 
-enum TokenKind {
+struct Token<'a> {
+    text: &'a str,
+    span: Span,
+}
+
+enum TokenKind<N> {
     Operator(String),
-    Operand(f64),
+    Operand(N),
+    Variable(String),
 }
 
 fn is_exoression(exoression: &str) -> bool {
-    !exoression.is_empty()
+    !exoression.trim().is_empty()
+}
+
+// 空白で区切り、それぞれの位置を記録する
+fn tokenize(exoression: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in exoression.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                let span = Span::new(s, i, tokens.len());
+                tokens.push(Token {
+                    text: &exoression[s..i],
+                    span,
+                });
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        let span = Span::new(s, exoression.len(), tokens.len());
+        tokens.push(Token {
+            text: &exoression[s..],
+            span,
+        });
+    }
+    tokens
+}
+
+// 数値リテラルの文法
+//
+// number   := sign? ( mantissa exponent? | "inf" | "infinity" | "nan" )
+// mantissa := digits ( "." digits? )? | "." digits
+// exponent := ( "e" | "E" ) sign? digits
+// digits   := digit ( "_"? digit )*
+// sign     := "+" | "-"
+//
+// inf / infinity / nan は大文字小文字を区別しない
+const NUMBER_PATTERN: &str = r"^[+-]?(?:(?:[0-9](?:_?[0-9])*(?:\.(?:[0-9](?:_?[0-9])*)?)?|\.[0-9](?:_?[0-9])*)(?:[eE][+-]?[0-9](?:_?[0-9])*)?|(?i:inf|infinity|nan))$";
+
+// 変数名の文法
+//
+// identifier := ( letter | "_" ) ( letter | digit | "_" )*
+const IDENTIFIER_PATTERN: &str = r"^[A-Za-z_][A-Za-z0-9_]*$";
+
+fn number_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(NUMBER_PATTERN).unwrap())
+}
+
+fn is_number(word: &str) -> bool {
+    number_regex().is_match(word)
 }
 
-fn is_unavailable_character(checked_string: &str) -> bool {
-    // reは不正値
-    let re = Regex::new(r"[^+\-*/%^1234567890 ]").unwrap();
-    re.is_match(checked_string)
+fn identifier_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(IDENTIFIER_PATTERN).unwrap())
 }
 
-fn syntax_check(exoression: &str) -> Result<(), PolishError> {
+fn is_identifier(word: &str) -> bool {
+    identifier_regex().is_match(word)
+}
+
+fn is_unavailable_token<N: Number>(word: &str, registry: &OperatorRegistry<N>) -> bool {
+    N::parse_literal(word).is_none() && !registry.contains(word) && !is_identifier(word)
+}
+
+fn unavailable_character(token: &Token) -> PolishError {
+    PolishError::UseUnavailableCharacter {
+        token: token.text.to_string(),
+        span: token.span,
+    }
+}
+
+fn syntax_check<N: Number>(
+    exoression: &str,
+    tokens: &[Token],
+    registry: &OperatorRegistry<N>,
+) -> Result<(), PolishError> {
     if !is_exoression(exoression) {
-        Err(PolishError::NotEnteredExoression)
-    } else if is_unavailable_character(exoression) {
-        return Err(PolishError::UseUnavailableCharacter);
-    } else {
-        Ok(())
+        return Err(PolishError::NotEnteredExoression);
+    }
+    match tokens
+        .iter()
+        .find(|token| is_unavailable_token(token.text, registry))
+    {
+        Some(token) => Err(unavailable_character(token)),
+        None => Ok(()),
     }
 }
 
-fn parse_token(word: &str) -> Result<TokenKind, PolishError> {
-    match word.parse::<f64>() {
+fn parse_token<N: Number>(
+    token: &Token,
+    registry: &OperatorRegistry<N>,
+) -> Result<TokenKind<N>, PolishError> {
+    let word = token.text;
+    if let Some(opnd) = N::parse_literal(word) {
         // opnd
-        Ok(i) => Ok(TokenKind::Operand(i)),
+        Ok(TokenKind::Operand(opnd))
+    } else if registry.contains(word) {
         // ops
-        Err(_) => {
-            if !is_unavailable_character(word) {
-                Ok(TokenKind::Operator(word.to_string()))
-            } else {
-                Err(PolishError::UseUnavailableCharacter)
+        Ok(TokenKind::Operator(word.to_string()))
+    } else if is_identifier(word) {
+        // 変数
+        Ok(TokenKind::Variable(word.to_string()))
+    } else {
+        Err(unavailable_character(token))
+    }
+}
+
+/// A node of a parsed Polish notation expression.
+///
+/// `parse` builds this tree from an expression, and `Expr::eval` calculates it.
+/// Operator children are kept in the order they appear in the expression,
+/// so `- 5 2` has the children `[5, 2]`.
+///
+/// `span` is the location of the node's own token (the operator, for an operator node),
+/// and is `None` for nodes built by hand.
+/// two expressions are equal when their trees are equal, whatever their spans are.
+/// an `Expr` prints as Polish notation, so `parse(&expr.to_string())` gives the same tree.
+/// a variadic operator with more than two operands, such as `(+ 1 2 3)`, prints as `+ + 1 2 3`.
+/// `N` is the `Number` type of the operands, `f64` by default.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{parse, Expr};
+///
+/// let expr = parse("+ 5 1").unwrap();
+/// assert_eq!(
+///     expr,
+///     Expr::operator("+", vec![Expr::number(5.0), Expr::number(1.0)])
+/// );
+/// assert_eq!(expr.eval(), Ok(6.0));
+/// ```
+#[derive(Debug)]
+pub struct Expr<N = f64> {
+    pub kind: ExprKind<N>,
+    pub span: Option<Span>,
+}
+
+/// What an `Expr` node is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind<N = f64> {
+    Number(N),
+    Variable(String),
+    Operator { op: String, children: Vec<Expr<N>> },
+}
+
+// 深い木でもスタックが溢れないよう、比較・複製・破棄・書き出しは再帰せずに行う
+impl<N: PartialEq> PartialEq for Expr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pairs = vec![(self, other)];
+        while let Some((a, b)) = pairs.pop() {
+            match (&a.kind, &b.kind) {
+                (
+                    ExprKind::Operator { op, children },
+                    ExprKind::Operator {
+                        op: other_op,
+                        children: other_children,
+                    },
+                ) => {
+                    if op != other_op || children.len() != other_children.len() {
+                        return false;
+                    }
+                    pairs.extend(children.iter().zip(other_children));
+                }
+                (a, b) => {
+                    if a != b {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<N: Clone> Clone for Expr<N> {
+    fn clone(&self) -> Self {
+        enum Step<'e, N> {
+            Enter(&'e Expr<N>),
+            Build(&'e Expr<N>),
+        }
+        // 子を先に複製し、できた子から親を組み立てる
+        let mut steps = vec![Step::Enter(self)];
+        let mut built: Vec<Expr<N>> = vec![];
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Enter(expr) => match &expr.kind {
+                    ExprKind::Operator { children, .. } => {
+                        steps.push(Step::Build(expr));
+                        steps.extend(children.iter().rev().map(Step::Enter));
+                    }
+                    kind => built.push(Expr {
+                        kind: kind.clone(),
+                        span: expr.span,
+                    }),
+                },
+                Step::Build(expr) => {
+                    if let ExprKind::Operator { op, children } = &expr.kind {
+                        let children = built.split_off(built.len() - children.len());
+                        built.push(Expr {
+                            kind: ExprKind::Operator {
+                                op: op.clone(),
+                                children,
+                            },
+                            span: expr.span,
+                        });
+                    }
+                }
+            }
+        }
+        built.remove(0)
+    }
+}
+
+impl<N> Drop for Expr<N> {
+    fn drop(&mut self) {
+        // 子を取り出してから落とせば、子の drop は孫を持たない
+        let mut orphans = match &mut self.kind {
+            ExprKind::Operator { children, .. } => std::mem::take(children),
+            _ => return,
+        };
+        while let Some(mut expr) = orphans.pop() {
+            if let ExprKind::Operator { children, .. } = &mut expr.kind {
+                orphans.append(children);
+            }
+        }
+    }
+}
+
+// 書き出す途中の木の断片
+pub(crate) enum Piece<'e, N> {
+    Expr(&'e Expr<N>),
+    Text(String),
+    // 中置記法の単項の `-`。`--x` と読み違えないように、次が `-` で始まるなら空白を入れる
+    Minus,
+}
+
+// `expand` で節を書く順の断片に分け、スタックに積んで左から書き出す
+pub(crate) fn render_with<'e, N>(
+    root: &'e Expr<N>,
+    mut expand: impl FnMut(&'e Expr<N>, &mut Vec<Piece<'e, N>>),
+) -> String {
+    let mut out = String::new();
+    let mut stack = vec![Piece::Expr(root)];
+    let mut pieces = vec![];
+    let mut minus = false;
+    while let Some(piece) = stack.pop() {
+        match piece {
+            Piece::Expr(expr) => {
+                expand(expr, &mut pieces);
+                stack.extend(pieces.drain(..).rev());
+            }
+            Piece::Text(text) if text.is_empty() => {}
+            Piece::Text(text) => {
+                if minus && text.starts_with('-') {
+                    out.push(' ');
+                }
+                minus = false;
+                out.push_str(&text);
+            }
+            Piece::Minus => {
+                if minus {
+                    out.push(' ');
+                }
+                out.push('-');
+                minus = true;
+            }
+        }
+    }
+    out
+}
+
+// 可変長の演算子が 3 つ以上の被演算子を持つとき、括弧のない記法では左から畳み込んだ二項演算として書く
+pub(crate) fn folds<N: Number>(op: &str, children: usize) -> bool {
+    children > 2
+        && default_registry::<N>()
+            .get(op)
+            .is_some_and(Operator::is_variadic)
+}
+
+// `parse` で読み戻せる前置記法で書く
+impl<N: Number> fmt::Display for Expr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&render_with(self, |expr, pieces| match &expr.kind {
+            ExprKind::Operator { op, children } if folds::<N>(op, children.len()) => {
+                for _ in 1..children.len() {
+                    pieces.push(Piece::Text(format!("{} ", op)));
+                }
+                pieces.push(Piece::Expr(&children[0]));
+                for child in &children[1..] {
+                    pieces.push(Piece::Text(" ".to_string()));
+                    pieces.push(Piece::Expr(child));
+                }
+            }
+            ExprKind::Operator { op, children } => {
+                pieces.push(Piece::Text(op.clone()));
+                for child in children {
+                    pieces.push(Piece::Text(" ".to_string()));
+                    pieces.push(Piece::Expr(child));
+                }
             }
+            _ => pieces.push(Piece::Text(expr.token())),
+        }))
+    }
+}
+
+impl Expr {
+    /// create a variable node without a span.
+    ///
+    /// for a `Number` type other than `f64`, write `Expr { kind: ExprKind::Variable(name), span: None }`.
+    pub fn variable(name: &str) -> Self {
+        Expr {
+            kind: ExprKind::Variable(name.to_string()),
+            span: None,
         }
     }
 }
 
-fn calculate(a: f64, b: f64, ops: &str) -> Result<f64, PolishError> {
-    match ops {
-        "+" => Ok(a + b),
-        "-" => Ok(a - b),
-        "*" => Ok(a * b),
-        "/" => Ok(a / b),
-        "%" => Ok(a % b),
-        _ => Err(PolishError::FailedCalculate),
+impl<N: Number> Expr<N> {
+    /// create a number node without a span.
+    pub fn number(value: N) -> Self {
+        Expr {
+            kind: ExprKind::Number(value),
+            span: None,
+        }
+    }
+
+    /// the token of this node: the number, the variable name or the operator.
+    pub fn token(&self) -> String {
+        match &self.kind {
+            ExprKind::Number(n) => n.token(),
+            ExprKind::Variable(name) => name.clone(),
+            ExprKind::Operator { op, .. } => op.clone(),
+        }
+    }
+
+    /// create an operator node without a span.
+    pub fn operator(op: &str, children: Vec<Expr<N>>) -> Self {
+        Expr {
+            kind: ExprKind::Operator {
+                op: op.to_string(),
+                children,
+            },
+            span: None,
+        }
+    }
+
+    /// calculate the tree with the built-in operators, then return ans as `N` or `PolishError`.
+    ///
+    /// every variable is undefined, use `Expr::eval_in` to give them values.
+    pub fn eval(&self) -> Result<N, PolishError> {
+        self.eval_in(&Environment::new())
+    }
+
+    /// same as `Expr::eval`, but variables are looked up in `env`.
+    pub fn eval_in(&self, env: &Environment<N>) -> Result<N, PolishError> {
+        self.eval_with(default_registry(), env)
+    }
+
+    /// calculate the tree with the operators in `registry` and the variables in `env`,
+    /// then return ans as `N` or `PolishError`.
+    ///
+    /// use `Evaluator` to choose the `Mode` as well.
+    pub fn eval_with(
+        &self,
+        registry: &OperatorRegistry<N>,
+        env: &Environment<N>,
+    ) -> Result<N, PolishError> {
+        Evaluator::default().registry(registry).env(env).eval(self)
+    }
+}
+
+/// receive an exoression in Polish notation as `&str`, then `parse` return the tree as `Expr` or `PolishError`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::parse;
+///
+/// match parse("* + 5 1 2") {
+///     Ok(expr) => println!("{:?}", expr),
+///     Err(e) => eprintln!("{}", e),
+/// };
+/// ```
+pub fn parse(expression: &str) -> Result<Expr, PolishError> {
+    parse_with(default_registry(), expression)
+}
+
+/// same as `parse`, but operators are looked up in `registry` instead of the built-in operators.
+pub fn parse_with(registry: &OperatorRegistry, expression: &str) -> Result<Expr, PolishError> {
+    parse_polish(registry, expression, false)
+}
+
+// 前置記法は右から、後置記法は左からトークンを読む
+pub(crate) fn parse_polish<N: Number>(
+    registry: &OperatorRegistry<N>,
+    expression: &str,
+    postfix: bool,
+) -> Result<Expr<N>, PolishError> {
+    let tokens = tokenize(expression);
+    syntax_check(expression, &tokens, registry)?;
+
+    let mut nodes: Vec<Expr<N>> = vec![];
+    let ordered: Box<dyn Iterator<Item = &Token>> = if postfix {
+        Box::new(tokens.iter())
+    } else {
+        Box::new(tokens.iter().rev())
+    };
+
+    for token in ordered {
+        let kind = match parse_token(token, registry)? {
+            // 被演算子の場合
+            TokenKind::Operand(opnd) => ExprKind::Number(opnd),
+            // 変数の場合
+            TokenKind::Variable(name) => ExprKind::Variable(name),
+            // 演算子の場合
+            TokenKind::Operator(ops) => {
+                let arity = registry
+                    .arity(&ops)
+                    .ok_or_else(|| unavailable_character(token))?;
+                if nodes.len() < arity {
+                    return Err(PolishError::NotEnoughOperands {
+                        operator: ops,
+                        expected: arity,
+                        found: nodes.len(),
+                        span: Some(token.span),
+                    });
+                }
+                let mut children = nodes.split_off(nodes.len() - arity);
+                if !postfix {
+                    children.reverse();
+                }
+                ExprKind::Operator { op: ops, children }
+            }
+        };
+        nodes.push(Expr {
+            kind,
+            span: Some(token.span),
+        });
+    }
+
+    if nodes.len() == 1 {
+        Ok(nodes.remove(0))
+    } else {
+        // 最後に読んだ式のひとつ前に残った式を指す
+        Err(PolishError::TooManyOperands {
+            remaining: nodes.len() - 1,
+            span: nodes[nodes.len() - 2].span,
+        })
     }
 }
 
 /// receive an exoression in Polish notation as `&str`, then `pn` return ans as `f64` or `PolishError`.
+///
+/// ## number literals
+///
+/// - integers and decimals: `5`, `1.5`, `.5`, `2.`
+/// - exponent notation: `6.02e23`, `1E-3`
+/// - explicit signs: `+5`, `-1.5` (a lone `+` or `-` is an operator)
+/// - `_` between digits as a separator: `1_000_000`
+/// - `inf`, `infinity` and `nan`, case-insensitive and optionally signed
+///
+/// ## variables
+///
+/// any other word made of letters, digits and `_`, not starting with a digit, is a variable.
+/// `pn` has no variables set, so use `parse` and `Expr::eval_in` with an `Environment`.
+///
+/// ## operators
+///
+/// these are the operators of `OperatorRegistry::new`.
+/// use `pn_with` to add your own operators or replace the built-in ones.
+///
+/// binary operators take two operands `a` and `b`, written as `op a b`.
+///
+/// | operator | result |
+/// | -------- | ------ |
+/// | `+`      | `a + b` |
+/// | `-`      | `a - b` |
+/// | `*`      | `a * b` |
+/// | `/`      | `a / b` |
+/// | `%`      | remainder of `a / b`, with the sign of `a` (`% -7 2` is `-1`) |
+/// | `^`      | `a` to the power of `b` |
+/// | `//`     | integer division, `a / b` rounded toward negative infinity (`// -7 2` is `-4`) |
+/// | `mod`    | Euclidean modulo, always zero or positive (`mod -7 2` is `1`) |
+/// | `min`    | the smaller of `a` and `b` |
+/// | `max`    | the larger of `a` and `b` |
+///
+/// unary operators take one operand `a`, written as `op a`.
+///
+/// | operator | result |
+/// | -------- | ------ |
+/// | `neg`    | `-a` |
+/// | `abs`    | absolute value of `a` |
+/// | `sqrt`   | square root of `a` |
+/// | `ln`     | natural logarithm of `a` |
+/// | `log`    | base 10 logarithm of `a` |
+/// | `exp`    | `e` to the power of `a` |
+/// | `sin`    | sine of `a` in radians |
+/// | `cos`    | cosine of `a` in radians |
+/// | `tan`    | tangent of `a` in radians |
+/// | `floor`  | `a` rounded toward negative infinity |
+/// | `ceil`   | `a` rounded toward positive infinity |
+/// | `round`  | `a` rounded to the nearest integer, half away from zero |
+///
+/// ternary operators take three operands, written as `op a b c`.
+///
+/// | operator        | result |
+/// | --------------- | ------ |
+/// | `clamp x lo hi` | `x` restricted to `lo..=hi`, fails when `lo > hi` |
+/// | `fma a b c`     | `a * b + c` with a single rounding |
+///
+/// `/ 1 0` is `inf` and `% 1 0` is `NaN`, as `f64` does.
+/// use `Evaluator` with `Mode::Strict` to make them errors.
 /// 
 /// ## example
 /// 
@@ -110,52 +896,23 @@ fn calculate(a: f64, b: f64, ops: &str) -> Result<f64, PolishError> {
 /// };
 /// ```
 pub fn pn(expression: &str) -> Result<f64, PolishError> {
-    match syntax_check(expression) {
-        Ok(_) => {}
-        Err(e) => return Err(e),
-    }
-
-    let split_expression = expression.split_whitespace();
-    let mut operands: Vec<f64> = vec![];
-
-    for token in split_expression.rev() {
-        if cfg!(debug_assertions) {
-            println!("token(Only displayed when debug): {:?}", token);
-        }
-        match parse_token(token) {
-            Ok(kind) => {
-                let result = match kind {
-                    // 被演算子の場合
-                    TokenKind::Operand(opnd) => opnd,
-                    // 演算子の場合
-                    TokenKind::Operator(ops) => {
-                        if operands.len() < 2 {
-                            return Err(PolishError::NotEnoughOperands);
-                        }
-                        match calculate(
-                            operands[operands.len() - 1],
-                            operands[operands.len() - 2],
-                            &ops,
-                        ) {
-                            Ok(result) => {
-                                operands.drain(operands.len() - 2..operands.len());
-                                result
-                            }
-                            Err(e) => return Err(e),
-                        }
-                    }
-                };
-                operands.push(result);
-            }
-            Err(e) => return Err(e),
-        };
-    }
+    pn_with(default_registry(), expression)
+}
 
-    if operands.len() == 1 {
-        Ok(operands[operands.len() - 1])
-    } else {
-        Err(PolishError::FailedCalculate)
-    }
+/// same as `pn`, but operators are looked up in `registry` instead of the built-in operators.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_with, OperatorRegistry};
+///
+/// let mut registry = OperatorRegistry::new();
+/// registry.register_binary("hypot", f64::hypot);
+///
+/// assert_eq!(pn_with(&registry, "+ 1 hypot 3 4"), Ok(6.0));
+/// ```
+pub fn pn_with(registry: &OperatorRegistry, expression: &str) -> Result<f64, PolishError> {
+    parse_with(registry, expression)?.eval_with(registry, &Environment::new())
 }
 
 #[cfg(test)]
@@ -173,8 +930,22 @@ mod tests {
             ("1", Ok(1.0)),
             ("-1", Ok(-1.0)),
             // 以下エラーテスト
-            ("* [ 5 1 = 7  1", Err(PolishError::UseUnavailableCharacter)),
-            ("* + 5 1 - 7", Err(PolishError::NotEnoughOperands)),
+            (
+                "* [ 5 1 = 7  1",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "[".to_string(),
+                    span: Span::new(2, 3, 1),
+                }),
+            ),
+            (
+                "* + 5 1 - 7",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "-".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(8, 9, 4)),
+                }),
+            ),
             ("", Err(PolishError::NotEnteredExoression)),
         ];
         for exoression in exoressions {
@@ -183,6 +954,320 @@ mod tests {
         }
     }
 
+    #[test]
+    fn number_test() {
+        let exoressions = [
+            ("+ 1.5 2", Ok(3.5)),
+            (".5", Ok(0.5)),
+            ("2.", Ok(2.0)),
+            ("6.02e23", Ok(6.02e23)),
+            ("* 1E-3 -2", Ok(-0.002)),
+            ("+5", Ok(5.0)),
+            ("- +1.5e+1 -5", Ok(20.0)),
+            ("1_000_000", Ok(1_000_000.0)),
+            ("+ 1_0.0_5 0", Ok(10.05)),
+            ("inf", Ok(f64::INFINITY)),
+            ("-Infinity", Ok(f64::NEG_INFINITY)),
+            // 以下エラーテスト
+            (
+                "_1",
+                Err(PolishError::UndefinedVariable {
+                    name: "_1".to_string(),
+                    span: Some(Span::new(0, 2, 0)),
+                }),
+            ),
+            (
+                "1__0",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1__0".to_string(),
+                    span: Span::new(0, 4, 0),
+                }),
+            ),
+            (
+                "1_",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1_".to_string(),
+                    span: Span::new(0, 2, 0),
+                }),
+            ),
+            (
+                "1e",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1e".to_string(),
+                    span: Span::new(0, 2, 0),
+                }),
+            ),
+            (
+                ".",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: ".".to_string(),
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "1.2.3",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1.2.3".to_string(),
+                    span: Span::new(0, 5, 0),
+                }),
+            ),
+            (
+                "--1",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "--1".to_string(),
+                    span: Span::new(0, 3, 0),
+                }),
+            ),
+            (
+                "infx",
+                Err(PolishError::UndefinedVariable {
+                    name: "infx".to_string(),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "+ 1 $",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "$".to_string(),
+                    span: Span::new(4, 5, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn(exoression.0), exoression.1);
+        }
+        assert!(pn("nan").unwrap().is_nan());
+        assert!(pn("+ 1 -NaN").unwrap().is_nan());
+    }
+
+    #[test]
+    fn operator_test() {
+        let exoressions = [
+            ("^ 2 3", Ok(8.0)),
+            ("^ 4 0.5", Ok(2.0)),
+            ("^ 2 -1", Ok(0.5)),
+            ("^ ^ 2 3 2", Ok(64.0)),
+            ("% -7 2", Ok(-1.0)),
+            ("// 7 2", Ok(3.0)),
+            ("// -7 2", Ok(-4.0)),
+            ("// 7.5 2.5", Ok(3.0)),
+            ("mod 7 2", Ok(1.0)),
+            ("mod -7 2", Ok(1.0)),
+            ("mod 7 -2", Ok(1.0)),
+            ("mod -7.5 2", Ok(0.5)),
+            ("min 3 -2", Ok(-2.0)),
+            ("max 3 -2", Ok(3.0)),
+            ("+ min 1 2 max 3 4", Ok(5.0)),
+            // 以下エラーテスト
+            (
+                "min 1",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "min".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(0, 3, 0)),
+                }),
+            ),
+            (
+                "pow 2 3",
+                Err(PolishError::TooManyOperands {
+                    remaining: 2,
+                    span: Some(Span::new(4, 5, 1)),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn arity_test() {
+        let exoressions = [
+            ("neg 5", Ok(-5.0)),
+            ("neg neg 5", Ok(5.0)),
+            ("abs -2.5", Ok(2.5)),
+            ("sqrt 16", Ok(4.0)),
+            ("ln 1", Ok(0.0)),
+            ("log 1000", Ok(3.0)),
+            ("exp 0", Ok(1.0)),
+            ("sin 0", Ok(0.0)),
+            ("cos 0", Ok(1.0)),
+            ("tan 0", Ok(0.0)),
+            ("floor -1.5", Ok(-2.0)),
+            ("ceil -1.5", Ok(-1.0)),
+            ("round 2.5", Ok(3.0)),
+            ("round -2.5", Ok(-3.0)),
+            ("clamp 5 0 3", Ok(3.0)),
+            ("clamp -5 0 3", Ok(0.0)),
+            ("clamp 2 0 3", Ok(2.0)),
+            ("fma 2 3 4", Ok(10.0)),
+            ("+ sqrt 9 * 2 neg 3", Ok(-3.0)),
+            ("fma abs -2 + 1 2 clamp 9 0 1", Ok(7.0)),
+            // 以下エラーテスト
+            (
+                "sqrt",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "sqrt".to_string(),
+                    expected: 1,
+                    found: 0,
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "clamp 1 2",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "clamp".to_string(),
+                    expected: 3,
+                    found: 2,
+                    span: Some(Span::new(0, 5, 0)),
+                }),
+            ),
+            (
+                "fma 1 2",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "fma".to_string(),
+                    expected: 3,
+                    found: 2,
+                    span: Some(Span::new(0, 3, 0)),
+                }),
+            ),
+            (
+                "neg 1 2",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(6, 7, 2)),
+                }),
+            ),
+            (
+                "clamp 1 3 0",
+                Err(PolishError::DomainError {
+                    operator: Some("clamp".to_string()),
+                    span: Some(Span::new(0, 5, 0)),
+                }),
+            ),
+            (
+                "clamp 1 nan 0",
+                Err(PolishError::DomainError {
+                    operator: Some("clamp".to_string()),
+                    span: Some(Span::new(0, 5, 0)),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn(exoression.0), exoression.1);
+        }
+        assert!(pn("sqrt -1").unwrap().is_nan());
+    }
+
+    #[test]
+    fn parse_test() {
+        let expr = parse("* + 5 1 2").unwrap();
+        assert_eq!(
+            expr,
+            Expr::operator(
+                "*",
+                vec![
+                    Expr::operator("+", vec![Expr::number(5.0), Expr::number(1.0)]),
+                    Expr::number(2.0),
+                ],
+            )
+        );
+        assert_eq!(expr.eval(), Ok(12.0));
+        assert_eq!(parse("- 5 2").unwrap().eval(), pn("- 5 2"));
+        let unknown = Expr::operator("pow", vec![Expr::number(2.0), Expr::number(3.0)]);
+        assert_eq!(
+            unknown.eval(),
+            Err(PolishError::UnknownOperator {
+                operator: "pow".to_string(),
+                span: None,
+            })
+        );
+        assert_eq!(
+            parse("1 2"),
+            Err(PolishError::TooManyOperands {
+                remaining: 1,
+                span: Some(Span::new(2, 3, 1)),
+            })
+        );
+    }
+
+    #[test]
+    fn error_test() {
+        let too_many = Expr::operator(
+            "neg",
+            vec![Expr::number(1.0), Expr::number(2.0), Expr::number(3.0)],
+        );
+        assert_eq!(
+            too_many.eval(),
+            Err(PolishError::TooManyOperands {
+                remaining: 2,
+                span: None,
+            })
+        );
+        assert_eq!(pn(" \t "), Err(PolishError::NotEnteredExoression));
+
+        let e = pn("clamp 1 3 0").unwrap_err();
+        assert_eq!(e.code(), "E0009");
+        assert_eq!(
+            e.to_string(),
+            "operands out of the domain of `clamp` at 0..5"
+        );
+        assert_eq!(
+            pn("+ 1 2 3").unwrap_err().to_string(),
+            "too many operands: 1 left over at 6..7"
+        );
+
+        // std::error::Error として扱える
+        fn run(expression: &str) -> Result<f64, Box<dyn std::error::Error>> {
+            Ok(pn(expression)?)
+        }
+        assert_eq!(run("+ 1 2").unwrap(), 3.0);
+        assert!(run("+ 1").is_err());
+    }
+
+    #[test]
+    fn span_test() {
+        let expr = parse("  *  + 5\t1   2 ").unwrap();
+        assert_eq!(expr.span, Some(Span::new(2, 3, 0)));
+        match &expr.kind {
+            ExprKind::Operator { children, .. } => {
+                assert_eq!(children[0].span, Some(Span::new(5, 6, 1)));
+                assert_eq!(children[1].span, Some(Span::new(13, 14, 4)));
+            }
+            kind => panic!("{:?}", kind),
+        }
+
+        // 逆順に読んでも位置は左からのまま
+        let e = pn("+ 1 * 2 sqrt x").unwrap_err();
+        assert_eq!(e.span(), Some(Span::new(13, 14, 5)));
+        assert_eq!(e.to_string(), "undefined variable `x` at 13..14");
+
+        // 多バイト文字もバイト単位で数える
+        let e = pn("+ ２ 1").unwrap_err();
+        assert_eq!(
+            e,
+            PolishError::UseUnavailableCharacter {
+                token: "２".to_string(),
+                span: Span::new(2, 5, 1),
+            }
+        );
+        assert_eq!(e.to_string(), "use unavailable character in `２` at 2..5");
+
+        let e = pn("* 2 clamp 1 3 0").unwrap_err();
+        assert_eq!(e.span(), Some(Span::new(4, 9, 2)));
+        let e = pn("+ 1").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "not enough operands for `+`: expected 2, found 1 at 0..1"
+        );
+        assert_eq!(pn("").unwrap_err().span(), None);
+        assert_eq!(pn("   ").unwrap_err().span(), None);
+    }
+
     #[test]
     fn use_test() {
         match pn("+ 5 1") {
@@ -190,4 +1275,27 @@ mod tests {
             Err(e) => eprintln!("{}", e),
         };
     }
+
+    #[test]
+    fn deep_test() {
+        // 深く入れ子になった式でも、再帰しないのでスタックが溢れない
+        let depth = 100_000;
+        let sum = "+ ".repeat(depth) + &"1 ".repeat(depth + 1);
+        assert_eq!(pn(&sum), Ok((depth + 1) as f64));
+
+        let expr = parse(&("neg ".repeat(depth) + "1")).unwrap();
+        assert_eq!(expr.eval(), Ok(1.0));
+        assert_eq!(expr.clone(), expr);
+        assert_eq!(parse(&expr.to_string()), Ok(expr.clone()));
+        let registry = OperatorRegistry::new();
+        for notation in [Notation::Postfix, Notation::SExpression, Notation::Infix] {
+            let rendered = notation.render(&expr);
+            assert_eq!(notation.parse(&registry, &rendered), Ok(expr.clone()));
+        }
+
+        let sexpr = "(neg ".repeat(depth) + "1" + &")".repeat(depth);
+        let nested = Notation::SExpression.parse(&registry, &sexpr).unwrap();
+        assert_eq!(nested, expr);
+        assert_eq!(from_infix(&("-".repeat(depth) + "1")), Ok(expr));
+    }
 }
@@ -0,0 +1,323 @@
+use crate::{Number, OperatorRegistry, PolishError};
+use std::collections::BTreeMap;
+use std::f64::consts::LN_10;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// 独立した測定ごとに振る番号
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(0);
+
+/// A value with a standard uncertainty, such as `9.81±0.02`.
+///
+/// every operator propagates the uncertainty to first order.
+/// a `Measurement` remembers how much each independent measurement contributes to it,
+/// so using the same measurement twice is correlated: `- x x` is exactly `0±0`,
+/// and `* x x` has twice the relative uncertainty of `x`.
+/// each literal in an exoression is a measurement of its own, independent of the others,
+/// so set a value in the `Environment` to use it more than once.
+///
+/// a number literal without `±` is exact. `+-` can be written instead of `±`,
+/// and the literal is written without spaces. two measurements are equal when their values
+/// and uncertainties are equal, even if they come from different measurements.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, Environment, Evaluator, Measurement};
+///
+/// let ans = pn_as::<Measurement>("* 2 9.81±0.02").unwrap();
+/// assert_eq!(ans.value(), 19.62);
+/// assert_eq!(ans.uncertainty(), 0.04);
+/// assert_eq!(format!("{:.2}", ans), "19.62±0.04");
+///
+/// let mut env = Environment::new();
+/// env.set("x", Measurement::new(2.0, 0.1));
+/// let ans = Evaluator::default().env(&env).pn("- x x").unwrap();
+/// assert_eq!(ans, Measurement::exact(0.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    value: f64,
+    // 独立した測定ごとの、不確かさへの寄与 (偏微分 × 標準不確かさ)
+    terms: BTreeMap<u64, f64>,
+}
+
+impl Measurement {
+    /// a new measurement of `value` with the standard uncertainty `sigma`,
+    /// independent of every other measurement.
+    pub fn new(value: f64, sigma: f64) -> Self {
+        let mut terms = BTreeMap::new();
+        if sigma != 0.0 {
+            terms.insert(NEXT_SOURCE.fetch_add(1, Ordering::Relaxed), sigma.abs());
+        }
+        Measurement { value, terms }
+    }
+
+    /// a value without uncertainty.
+    pub fn exact(value: f64) -> Self {
+        Measurement {
+            value,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// the standard uncertainty.
+    pub fn uncertainty(&self) -> f64 {
+        self.terms.values().fold(0.0, |sum, c| sum + c * c).sqrt()
+    }
+
+    // f(a) の値と、a についての微分から寄与を求める
+    fn unary(&self, value: f64, da: f64) -> Self {
+        self.combine(value, da, &Measurement::exact(0.0), 0.0)
+    }
+
+    // f(a, b) の値と、a と b についての偏微分から寄与を足し合わせる
+    fn combine(&self, value: f64, da: f64, b: &Measurement, db: f64) -> Self {
+        let mut terms = BTreeMap::new();
+        for (&source, &c) in &self.terms {
+            *terms.entry(source).or_insert(0.0) += da * c;
+        }
+        for (&source, &c) in &b.terms {
+            *terms.entry(source).or_insert(0.0) += db * c;
+        }
+        Measurement { value, terms }
+    }
+
+    // 商 q を使って a - b * q と表せる剰余の演算
+    fn remainder(&self, b: &Measurement, r: f64) -> Self {
+        let q = ((self.value - r) / b.value).round();
+        self.combine(r, 1.0, b, -q)
+    }
+}
+
+/// prints `value±sigma`. a precision, such as `{:.2}`, is used for both.
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*}±{:.*}", p, self.value, p, self.uncertainty()),
+            None => write!(f, "{}±{}", self.value, self.uncertainty()),
+        }
+    }
+}
+
+impl PartialEq for Measurement {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.uncertainty() == other.uncertainty()
+    }
+}
+
+impl Number for Measurement {
+    fn parse_literal(token: &str) -> Option<Self> {
+        let Some((value, sigma)) = token.split_once('±').or_else(|| token.split_once("+-")) else {
+            return f64::parse_literal(token).map(Measurement::exact);
+        };
+        let sigma = f64::parse_literal(sigma).filter(|sigma| *sigma >= 0.0)?;
+        Some(Measurement::new(f64::parse_literal(value)?, sigma))
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register_binary("+", |a, b| a.combine(a.value + b.value, 1.0, &b, 1.0))
+            .register_binary("-", |a, b| a.combine(a.value - b.value, 1.0, &b, -1.0))
+            .register_binary("*", |a, b| {
+                a.combine(a.value * b.value, b.value, &b, a.value)
+            })
+            .register_binary("/", |a, b| {
+                let value = a.value / b.value;
+                a.combine(value, 1.0 / b.value, &b, -value / b.value)
+            })
+            .register_binary("%", |a, b| a.remainder(&b, a.value % b.value))
+            .register_binary("mod", |a, b| a.remainder(&b, a.value.rem_euclid(b.value)))
+            .register_binary("//", |a, b| a.unary((a.value / b.value).floor(), 0.0))
+            .register_binary("^", |a, b| {
+                let value = a.value.powf(b.value);
+                // 0 乗は定数なので、底が 0 でも 0 * inf にしない
+                let da = if b.value == 0.0 {
+                    0.0
+                } else {
+                    b.value * a.value.powf(b.value - 1.0)
+                };
+                // 指数が正確なら、底が負でも ln を使わずに済む
+                let db = if b.terms.is_empty() {
+                    0.0
+                } else {
+                    value * a.value.ln()
+                };
+                a.combine(value, da, &b, db)
+            })
+            .register_binary("min", |a, b| if b.value < a.value { b } else { a })
+            .register_binary("max", |a, b| if b.value > a.value { b } else { a })
+            .register_unary("neg", |a| a.unary(-a.value, -1.0))
+            .register_unary("abs", |a| a.unary(a.value.abs(), a.value.signum()))
+            .register_unary("sqrt", |a| {
+                let value = a.value.sqrt();
+                a.unary(value, 0.5 / value)
+            })
+            .register_unary("ln", |a| a.unary(a.value.ln(), 1.0 / a.value))
+            .register_unary("log", |a| a.unary(a.value.log10(), 1.0 / (a.value * LN_10)))
+            .register_unary("exp", |a| {
+                let value = a.value.exp();
+                a.unary(value, value)
+            })
+            .register_unary("sin", |a| a.unary(a.value.sin(), a.value.cos()))
+            .register_unary("cos", |a| a.unary(a.value.cos(), -a.value.sin()))
+            .register_unary("tan", |a| {
+                let cos = a.value.cos();
+                a.unary(a.value.tan(), 1.0 / (cos * cos))
+            })
+            .register_unary("floor", |a| a.unary(a.value.floor(), 0.0))
+            .register_unary("ceil", |a| a.unary(a.value.ceil(), 0.0))
+            .register_unary("round", |a| a.unary(a.value.round(), 0.0))
+            .register("clamp", 3, |x| {
+                if x[1].value <= x[2].value {
+                    Ok(if x[0].value < x[1].value {
+                        x[1].clone()
+                    } else if x[0].value > x[2].value {
+                        x[2].clone()
+                    } else {
+                        x[0].clone()
+                    })
+                } else {
+                    Err(PolishError::DomainError {
+                        operator: None,
+                        span: None,
+                    })
+                }
+            })
+            .register("fma", 3, |x| {
+                let product = x[0].combine(x[0].value * x[1].value, x[1].value, &x[1], x[0].value);
+                Ok(product.combine(x[0].value.mul_add(x[1].value, x[2].value), 1.0, &x[2], 1.0))
+            })
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value == 0.0
+    }
+
+    fn is_finite(&self) -> bool {
+        self.value.is_finite() && self.uncertainty().is_finite()
+    }
+
+    fn is_nan(&self) -> bool {
+        self.value.is_nan() || self.uncertainty().is_nan()
+    }
+
+    // `9.81±0.02` は `±` の後の数までをひとつのリテラルにする。
+    // 中置記法の `1+-2` は `1 + -2` なので、`+-` は含めない
+    fn scan_literal(text: &str) -> Option<usize> {
+        let value = f64::scan_literal(text)?;
+        let sigma = text[value..]
+            .strip_prefix('±')
+            .and_then(f64::scan_literal)
+            .map_or(0, |sigma| '±'.len_utf8() + sigma);
+        Some(value + sigma)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn_as, Environment, Evaluator, Mode, Notation, Span};
+    use std::f64::consts::LN_2;
+
+    #[test]
+    fn measurement_test() {
+        let m = |value, sigma| Ok(Measurement::new(value, sigma));
+        let exoressions = [
+            ("+ 1±3 2±4", m(3.0, 5.0)),
+            ("- 1±3 2±4", m(-1.0, 5.0)),
+            ("* 2±0.5 3", m(6.0, 1.5)),
+            ("* 3±1 4±1", m(12.0, 5.0)),
+            ("/ 12±3 4", m(3.0, 0.75)),
+            ("/ 12±4 4±1", m(3.0, 1.25)),
+            ("^ 3±0.5 2", m(9.0, 3.0)),
+            ("^ 0±1 0", m(1.0, 0.0)),
+            ("% 7±1 2", m(1.0, 1.0)),
+            ("sqrt 16±4", m(4.0, 0.5)),
+            ("neg 1±2", m(-1.0, 2.0)),
+            ("abs -2±1", m(2.0, 1.0)),
+            ("ln 2±1", m(LN_2, 0.5)),
+            ("exp 0±1", m(1.0, 1.0)),
+            ("sin 0±0.5", m(0.0, 0.5)),
+            ("cos 0±0.5", m(1.0, 0.0)),
+            ("floor 1.5±1", m(1.0, 0.0)),
+            ("min 1±2 3±4", m(1.0, 2.0)),
+            ("+ 1+-3 2", m(3.0, 3.0)),
+            ("+ 1 2", m(3.0, 0.0)),
+            // 別々に書いたリテラルは独立な測定になる
+            ("- 3±1 3±1", m(0.0, 2.0_f64.sqrt())),
+            // 以下エラーテスト
+            (
+                "+ 1 2+--1",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "2+--1".to_string(),
+                    span: Span::new(4, 9, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn_as::<Measurement>(exoression.0), exoression.1);
+        }
+
+        assert!(matches!(
+            Evaluator::<Measurement>::default()
+                .mode(Mode::Strict)
+                .pn("/ 1 0±1"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn correlation_test() {
+        let mut env = Environment::new();
+        env.set("x", Measurement::new(3.0, 1.0));
+        let exoressions = [
+            ("- x x", Measurement::exact(0.0)),
+            ("/ x x", Measurement::exact(1.0)),
+            ("+ x x", Measurement::new(6.0, 2.0)),
+            ("* x x", Measurement::new(9.0, 6.0)),
+            ("^ x 2", Measurement::new(9.0, 6.0)),
+            ("- * 2 x x", Measurement::new(3.0, 1.0)),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let ans = Evaluator::default().env(&env).pn(exoression.0);
+            assert_eq!(ans, Ok(exoression.1));
+        }
+    }
+
+    #[test]
+    fn format_test() {
+        let exoressions = [
+            (Measurement::new(9.81, 0.02), "9.81±0.02", "9.810±0.020"),
+            (Measurement::new(-1.5, -0.25), "-1.5±0.25", "-1.500±0.250"),
+            (Measurement::exact(2.0), "2±0", "2.000±0.000"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(exoression.0.to_string(), exoression.1);
+            assert_eq!(format!("{:.3}", exoression.0), exoression.2);
+            assert_eq!(Measurement::parse_literal(exoression.1), Some(exoression.0));
+        }
+
+        let registry = OperatorRegistry::<Measurement>::new();
+        let expr = Notation::Infix.parse(&registry, "9.81±0.02 * 2").unwrap();
+        assert_eq!(expr.eval(), Ok(Measurement::new(19.62, 0.04)));
+        // 中置記法の `+-` は足し算と符号
+        let expr = Notation::Infix.parse(&registry, "3+-1").unwrap();
+        assert_eq!(expr.eval(), Ok(Measurement::exact(2.0)));
+    }
+}
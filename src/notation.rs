@@ -0,0 +1,509 @@
+use crate::infix::parse_infix;
+use crate::registry::default_registry;
+use crate::sexpr::{parse_sexpr, write_sexpr};
+use crate::{
+    folds, parse_polish, render_with, Evaluator, Expr, ExprKind, Number, OperatorRegistry, Piece,
+    PolishError,
+};
+
+/// The order of operators and operands in an exoression.
+///
+/// every notation uses the same operators and errors, and keeps operands in the same order,
+/// so `- 5 2`, `5 2 -`, `(- 5 2)` and `5 - 2` are the same `Expr`.
+/// `Notation::parse` reads an exoression into an `Expr`, and `Notation::render` writes it back,
+/// so `convert` can move a formula from one notation to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    /// Polish notation, the operator comes before its operands: `* + 5 1 2`.
+    #[default]
+    Prefix,
+    /// Reverse Polish notation, the operator comes after its operands: `5 1 + 2 *`.
+    Postfix,
+    /// Cambridge Polish notation, every operator is put in parentheses with its operands: `(* (+ 5 1) 2)`.
+    ///
+    /// when reading, the parentheses are optional, so `* (+ 5 1) 2` and `* + 5 1 2` are read as well.
+    /// in parentheses, a variadic operator such as `+`, `*`, `min` or `max` takes any number of operands:
+    /// `(+ 1 2 3 4)`. a parenthesis without a pair is `UnbalancedParenthesis` with its position.
+    SExpression,
+    /// infix notation, read by `from_infix` and written by `Expr::to_infix`: `(5 + 1) * 2`.
+    Infix,
+}
+
+impl Notation {
+    /// every notation, in the order they are declared.
+    pub const ALL: [Notation; 4] = [
+        Notation::Prefix,
+        Notation::Postfix,
+        Notation::SExpression,
+        Notation::Infix,
+    ];
+
+    /// parse `expression` written in this notation with the operators in `registry`.
+    ///
+    /// ## example
+    ///
+    /// ```
+    /// use polish_notation::{parse, Notation, OperatorRegistry};
+    ///
+    /// let registry = OperatorRegistry::new();
+    /// let postfix = Notation::Postfix.parse(&registry, "5 1 + 2 *").unwrap();
+    /// assert_eq!(postfix, parse("* + 5 1 2").unwrap());
+    /// ```
+    pub fn parse<N: Number>(
+        self,
+        registry: &OperatorRegistry<N>,
+        expression: &str,
+    ) -> Result<Expr<N>, PolishError> {
+        match self {
+            Notation::Prefix => parse_polish(registry, expression, false),
+            Notation::Postfix => parse_polish(registry, expression, true),
+            Notation::SExpression => parse_sexpr(registry, expression),
+            Notation::Infix => parse_infix(registry, expression),
+        }
+    }
+
+    /// write `expr` in this notation.
+    ///
+    /// ## example
+    ///
+    /// ```
+    /// use polish_notation::{parse, Notation};
+    ///
+    /// let expr = parse("* + 5 1 2").unwrap();
+    /// assert_eq!(Notation::Postfix.render(&expr), "5 1 + 2 *");
+    /// assert_eq!(Notation::SExpression.render(&expr), "(* (+ 5 1) 2)");
+    /// ```
+    pub fn render<N: Number>(self, expr: &Expr<N>) -> String {
+        match self {
+            Notation::Prefix => expr.to_string(),
+            Notation::Postfix => write_postfix(expr),
+            Notation::SExpression => write_sexpr(expr),
+            Notation::Infix => expr.to_infix(),
+        }
+    }
+
+    /// the name of this notation: `prefix`, `postfix`, `sexpr` or `infix`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Notation::Prefix => "prefix",
+            Notation::Postfix => "postfix",
+            Notation::SExpression => "sexpr",
+            Notation::Infix => "infix",
+        }
+    }
+
+    /// find a notation by its name, or by `pn`, `polish`, `rpn`, `s-expression` or `cambridge`.
+    ///
+    /// the name is not case-sensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "prefix" | "pn" | "polish" => Some(Notation::Prefix),
+            "postfix" | "rpn" => Some(Notation::Postfix),
+            "sexpr" | "s-expression" | "cambridge" => Some(Notation::SExpression),
+            "infix" => Some(Notation::Infix),
+            _ => None,
+        }
+    }
+
+    // 後置記法と中置記法は左のトークンから計算する
+    pub(crate) fn left_to_right(self) -> bool {
+        matches!(self, Notation::Postfix | Notation::Infix)
+    }
+}
+
+fn write_postfix<N: Number>(expr: &Expr<N>) -> String {
+    render_with(expr, |expr, pieces| match &expr.kind {
+        // `1 2 + 3 +` のように左から畳み込む
+        ExprKind::Operator { op, children } if folds::<N>(op, children.len()) => {
+            pieces.push(Piece::Expr(&children[0]));
+            for child in &children[1..] {
+                pieces.push(Piece::Text(" ".to_string()));
+                pieces.push(Piece::Expr(child));
+                pieces.push(Piece::Text(format!(" {}", op)));
+            }
+        }
+        ExprKind::Operator { children, .. } => {
+            for child in children {
+                pieces.push(Piece::Expr(child));
+                pieces.push(Piece::Text(" ".to_string()));
+            }
+            pieces.push(Piece::Text(expr.token()));
+        }
+        _ => pieces.push(Piece::Text(expr.token())),
+    })
+}
+
+/// receive an exoression written in `from`, then `convert` return it written in `to`,
+/// as `String` or `PolishError`.
+///
+/// the exoression is parsed into an `Expr` first, so converting back gives the same tree.
+/// a negative number is written in infix as `(-1)`, so it comes back as the same number.
+/// `+ 1 + 2 3` written in infix comes back as `+ + 1 2 3`,
+/// and `(+ 1 2 3)` written in prefix or postfix comes back as `(+ (+ 1 2) 3)`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{convert, Notation};
+///
+/// let prefix = convert("(5 + 1) * 2", Notation::Infix, Notation::Prefix).unwrap();
+/// assert_eq!(prefix, "* + 5 1 2");
+/// let sexpr = convert(&prefix, Notation::Prefix, Notation::SExpression).unwrap();
+/// assert_eq!(sexpr, "(* (+ 5 1) 2)");
+/// ```
+pub fn convert(expression: &str, from: Notation, to: Notation) -> Result<String, PolishError> {
+    convert_with(default_registry::<f64>(), expression, from, to)
+}
+
+/// same as `convert`, but operators are looked up in `registry`,
+/// and number literals are read as its `Number` type.
+pub fn convert_with<N: Number>(
+    registry: &OperatorRegistry<N>,
+    expression: &str,
+    from: Notation,
+    to: Notation,
+) -> Result<String, PolishError> {
+    Ok(to.render(&from.parse(registry, expression)?))
+}
+
+/// same as `parse`, but `expression` is in Reverse Polish (postfix) notation.
+pub fn parse_rpn(expression: &str) -> Result<Expr, PolishError> {
+    Notation::Postfix.parse(default_registry(), expression)
+}
+
+/// receive an exoression in Reverse Polish (postfix) notation as `&str`, then `rpn` return ans as `f64` or `PolishError`.
+///
+/// the operators are the same as `pn`, and operands keep their order, so `5 2 -` is `3`.
+/// use `Evaluator::notation` for variables, your own operators or `Mode::Strict`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::rpn;
+///
+/// assert_eq!(rpn("5 1 + 2 *"), Ok(12.0));
+/// assert_eq!(rpn("5 2 -"), Ok(3.0));
+/// ```
+pub fn rpn(expression: &str) -> Result<f64, PolishError> {
+    Evaluator::new().notation(Notation::Postfix).pn(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn, pn_trace, Environment, Mode, Span};
+
+    #[test]
+    fn rpn_test() {
+        let exoressions = [
+            ("5 2 +", Ok(7.0)),
+            ("5 2 -", Ok(3.0)),
+            ("5 2 /", Ok(2.5)),
+            ("2 3 ^", Ok(8.0)),
+            ("-7 2 mod", Ok(1.0)),
+            ("5 1 + 2 *", Ok(12.0)),
+            ("16 sqrt neg", Ok(-4.0)),
+            ("5 0 3 clamp", Ok(3.0)),
+            ("1.5e1 -5 -", Ok(20.0)),
+            ("1", Ok(1.0)),
+            // 以下エラーテスト
+            (
+                "1 -",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "-".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(2, 3, 1)),
+                }),
+            ),
+            (
+                "1 2 3 +",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "1 $ +",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "$".to_string(),
+                    span: Span::new(2, 3, 1),
+                }),
+            ),
+            ("", Err(PolishError::NotEnteredExoression)),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(rpn(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn same_as_prefix_test() {
+        let pairs = [
+            ("- / 9 3 1", "9 3 / 1 -"),
+            ("fma 2 3 4", "2 3 4 fma"),
+            ("% - 10 3 4", "10 3 - 4 %"),
+            ("max neg 1 abs -2", "1 neg -2 abs max"),
+        ];
+        for (prefix, postfix) in pairs {
+            assert_eq!(parse_rpn(postfix).unwrap(), crate::parse(prefix).unwrap());
+            assert_eq!(rpn(postfix), pn(prefix));
+        }
+    }
+
+    #[test]
+    fn evaluator_test() {
+        let mut env = Environment::new();
+        env.set("x", 2.0);
+        let postfix = Evaluator::new()
+            .env(&env)
+            .mode(Mode::Strict)
+            .notation(Notation::Postfix);
+        assert_eq!(postfix.pn("x 3 ^"), Ok(8.0));
+        assert!(matches!(
+            postfix.pn("x 0 /"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+
+        // 後置記法の途中経過は左から読んだ順になる
+        let steps = postfix.pn_trace("x 3 - 4 *").unwrap();
+        let tokens: Vec<&str> = steps.iter().map(|step| step.token.as_str()).collect();
+        assert_eq!(tokens, ["x", "3", "-", "4", "*"]);
+        assert_eq!(steps[2].operands, [2.0, 3.0]);
+        assert_eq!(steps[2].stack, [-1.0]);
+        assert_eq!(steps[3].stack, [-1.0, 4.0]);
+        assert_eq!(pn_trace("* - 2 3 4").unwrap()[0].token, "4");
+    }
+
+    #[test]
+    fn sexpr_test() {
+        let exoressions = [
+            ("(+ 5 (* 2 3))", Ok(11.0)),
+            ("(- 5 2)", Ok(3.0)),
+            ("(clamp (neg 5) 0 (sqrt 9))", Ok(0.0)),
+            ("  ( max 1\n  (min 2 3) )", Ok(2.0)),
+            ("(+(* 2 3)1)", Ok(7.0)),
+            ("42", Ok(42.0)),
+            // 以下エラーテスト
+            (
+                "(+ 1 2",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "(+ 1 2))",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(7, 8, 5),
+                }),
+            ),
+            (
+                "(+ 1)",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "+".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(1, 2, 1)),
+                }),
+            ),
+            (
+                "(- 1 2 3 4)",
+                Err(PolishError::TooManyOperands {
+                    remaining: 2,
+                    span: Some(Span::new(7, 8, 4)),
+                }),
+            ),
+            (
+                "(+ 1 2) 3",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(8, 9, 5)),
+                }),
+            ),
+            (
+                "(1 2)",
+                Err(PolishError::UnexpectedToken {
+                    token: "2".to_string(),
+                    span: Span::new(3, 4, 2),
+                }),
+            ),
+            (
+                "(f 2)",
+                Err(PolishError::UnknownOperator {
+                    operator: "f".to_string(),
+                    span: Some(Span::new(1, 2, 1)),
+                }),
+            ),
+            (
+                "+",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "+".to_string(),
+                    expected: 2,
+                    found: 0,
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "(+ 1 $)",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "$".to_string(),
+                    span: Span::new(5, 6, 3),
+                }),
+            ),
+            ("", Err(PolishError::NotEnteredExoression)),
+        ];
+        let sexpr = Evaluator::new().notation(Notation::SExpression);
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(sexpr.pn(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn cambridge_test() {
+        let exoressions = [
+            ("(+ 1 2 3 4)", Ok(10.0)),
+            ("(* 2 3 4)", Ok(24.0)),
+            ("(min 5 2 8)", Ok(2.0)),
+            ("(max 1 (+ 1 1 1) 2)", Ok(3.0)),
+            ("(- (+ 1 2 3) 1)", Ok(5.0)),
+            // 括弧は省略できる
+            ("* (+ 1 2 3) 2", Ok(12.0)),
+            ("* + 1 2 3", Ok(9.0)),
+            ("(+ * 2 3 4 5)", Ok(15.0)),
+            ("((+ 1 2))", Ok(3.0)),
+            ("(x)", Ok(0.5)),
+            // 以下エラーテスト
+            (
+                "(- 1 2 3)",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(7, 8, 4)),
+                }),
+            ),
+            (
+                "+ 1 2 3",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(6, 7, 3)),
+                }),
+            ),
+            (
+                "(+ 1)",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "+".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(1, 2, 1)),
+                }),
+            ),
+            (
+                "(+ 1 (* 2 3 4)",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "(+ 1 (* 2",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(5, 6, 3),
+                }),
+            ),
+            (
+                "(+ 1 - 2)",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "-".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(5, 6, 3)),
+                }),
+            ),
+            (
+                "* 2 (+ 1 2))",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(11, 12, 7),
+                }),
+            ),
+            (
+                "()",
+                Err(PolishError::UnexpectedToken {
+                    token: ")".to_string(),
+                    span: Span::new(1, 2, 1),
+                }),
+            ),
+        ];
+        let mut env = Environment::new();
+        env.set("x", 0.5);
+        let cambridge = Evaluator::new().env(&env).notation(Notation::SExpression);
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(cambridge.pn(exoression.0), exoression.1);
+        }
+
+        // 被演算子が 3 つ以上の可変長の演算子は、括弧のない記法では左から畳み込む
+        let expr = Notation::SExpression
+            .parse(&OperatorRegistry::new(), "(* (+ 1 2 3) (max a b c))")
+            .unwrap();
+        assert_eq!(
+            Notation::SExpression.render(&expr),
+            "(* (+ 1 2 3) (max a b c))"
+        );
+        assert_eq!(expr.to_string(), "* + + 1 2 3 max max a b c");
+        assert_eq!(Notation::Postfix.render(&expr), "1 2 + 3 + a b max c max *");
+        assert_eq!(expr.to_infix(), "(1 + 2 + 3) * max(a, b, c)");
+        assert_eq!(
+            convert("(- 10 (+ 1 2 3))", Notation::SExpression, Notation::Infix),
+            Ok("10 - (1 + 2 + 3)".to_string())
+        );
+        for notation in [Notation::Prefix, Notation::Postfix, Notation::Infix] {
+            let written = notation.render(&expr);
+            let env: Environment = [("a", 1.0), ("b", 4.0), ("c", 2.0)].into_iter().collect();
+            let back = notation.parse(&OperatorRegistry::new(), &written).unwrap();
+            assert_eq!(back.eval_in(&env), expr.eval_in(&env));
+        }
+    }
+
+    #[test]
+    fn convert_test() {
+        let exoressions = [
+            ["* + 5 1 2", "5 1 + 2 *", "(* (+ 5 1) 2)", "(5 + 1) * 2"],
+            ["- x - 1 2", "x 1 2 - -", "(- x (- 1 2))", "x - (1 - 2)"],
+            ["^ 2 ^ 3 2", "2 3 2 ^ ^", "(^ 2 (^ 3 2))", "2 ^ 3 ^ 2"],
+            [
+                "clamp neg x 0 sqrt 9",
+                "x neg 0 9 sqrt clamp",
+                "(clamp (neg x) 0 (sqrt 9))",
+                "clamp(-x, 0, sqrt(9))",
+            ],
+            ["1.5", "1.5", "1.5", "1.5"],
+        ];
+        for written in exoressions {
+            println!("{:?}", written);
+            for (from, expression) in Notation::ALL.into_iter().zip(written) {
+                for (to, expected) in Notation::ALL.into_iter().zip(written) {
+                    assert_eq!(convert(expression, from, to).as_deref(), Ok(expected));
+                }
+            }
+        }
+        assert_eq!(
+            convert("(+ 1", Notation::SExpression, Notation::Prefix),
+            Err(PolishError::UnbalancedParenthesis {
+                span: Span::new(0, 1, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn name_test() {
+        for notation in Notation::ALL {
+            assert_eq!(Notation::from_name(notation.name()), Some(notation));
+        }
+        assert_eq!(Notation::from_name("RPN"), Some(Notation::Postfix));
+        assert_eq!(
+            Notation::from_name("cambridge"),
+            Some(Notation::SExpression)
+        );
+        assert_eq!(Notation::from_name("lisp"), None);
+    }
+}
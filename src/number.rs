@@ -0,0 +1,549 @@
+use crate::infix::scan_number;
+use crate::{is_number, Evaluator, OperatorRegistry, PolishError};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// A type of number that expressions can be evaluated as.
+///
+/// each backend decides how a number literal is read and which operators are built in,
+/// so the same exoression can be evaluated as `f64`, `f32`, `i64`, `BigInt`, `Rational`,
+/// `BigDecimal`, `Complex64`, `Interval`, `Measurement` or `Quantity`.
+/// `OperatorRegistry::new` calls `Number::builtins`, and `Mode::Strict` uses
+/// `is_zero`, `is_finite` and `is_nan`. infix notation reads literals with `scan_literal`
+/// and parenthesizes them with `literal_operator`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, BigInt, Evaluator, PolishError};
+///
+/// assert_eq!(pn_as::<f64>("/ 7 2"), Ok(3.5));
+/// assert_eq!(pn_as::<f32>("/ 7 2"), Ok(3.5));
+/// assert_eq!(pn_as::<i64>("/ 7 2"), Ok(3));
+///
+/// // `i64` fails instead of wrapping around, and `BigInt` never overflows
+/// assert!(matches!(
+///     pn_as::<i64>("^ 2 100"),
+///     Err(PolishError::IntegerOverflow { .. })
+/// ));
+/// assert_eq!(
+///     pn_as::<BigInt>("^ 2 100").map(|ans| ans.to_string()),
+///     Ok("1267650600228229401496703205376".to_string())
+/// );
+///
+/// // `sqrt` is not an operator of `i64`, so it is read as a variable
+/// assert!(matches!(
+///     Evaluator::<i64>::default().pn("sqrt 4"),
+///     Err(PolishError::TooManyOperands { .. })
+/// ));
+/// ```
+pub trait Number: Clone + PartialEq + fmt::Debug + fmt::Display + Send + Sync + 'static {
+    /// read `token` as a number literal, or `None` when it is not one.
+    fn parse_literal(token: &str) -> Option<Self>;
+
+    /// register the built-in operators of this type in `registry`.
+    fn builtins(registry: &mut OperatorRegistry<Self>);
+
+    /// whether this is zero, which `Mode::Strict` rejects as a divisor.
+    fn is_zero(&self) -> bool;
+
+    /// whether this is a finite number. `Mode::Strict` rejects the others. always `true` by default.
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    /// whether this is NaN. always `false` by default.
+    fn is_nan(&self) -> bool {
+        false
+    }
+
+    /// this number as a token of an exoression, which `parse_literal` reads back.
+    /// `to_string` by default.
+    fn token(&self) -> String {
+        self.to_string()
+    }
+
+    /// the length of the number literal at the start of `text` in infix notation,
+    /// or `None` when `text` does not start with one. a sign is read as an operator in infix notation,
+    /// so it is not a part of the literal. a decimal literal, such as `2.5e-3`, by default.
+    fn scan_literal(text: &str) -> Option<usize> {
+        scan_number(text)
+    }
+
+    /// the infix operator that this number binds like when it is written as a literal,
+    /// such as `+` for `2+3i`, so that it is parenthesized like that operator.
+    /// `None` needs no parentheses. `neg` for a negative number by default.
+    fn literal_operator(&self) -> Option<&'static str> {
+        self.token().starts_with('-').then_some("neg")
+    }
+}
+
+// f64 と f32 は同じ演算子を持つ
+macro_rules! float_number {
+    ($t:ident) => {
+        impl Number for $t {
+            fn parse_literal(token: &str) -> Option<Self> {
+                // 桁区切りの `_` は浮動小数点数のパーサーが受け付けないので取り除く
+                if is_number(token) {
+                    token.replace('_', "").parse().ok()
+                } else {
+                    None
+                }
+            }
+
+            fn builtins(registry: &mut OperatorRegistry<Self>) {
+                registry
+                    .register_binary("+", |a, b| a + b)
+                    .register_binary("-", |a, b| a - b)
+                    .register_binary("*", |a, b| a * b)
+                    .register_binary("/", |a, b| a / b)
+                    .register_binary("%", |a, b| a % b)
+                    .register_binary("^", $t::powf)
+                    .register_binary("//", |a, b| (a / b).floor())
+                    .register_binary("mod", $t::rem_euclid)
+                    .register_binary("min", $t::min)
+                    .register_binary("max", $t::max)
+                    .register_unary("neg", |a| -a)
+                    .register_unary("abs", $t::abs)
+                    .register_unary("sqrt", $t::sqrt)
+                    .register_unary("ln", $t::ln)
+                    .register_unary("log", $t::log10)
+                    .register_unary("exp", $t::exp)
+                    .register_unary("sin", $t::sin)
+                    .register_unary("cos", $t::cos)
+                    .register_unary("tan", $t::tan)
+                    .register_unary("floor", $t::floor)
+                    .register_unary("ceil", $t::ceil)
+                    .register_unary("round", $t::round)
+                    .register("clamp", 3, |x| {
+                        // clamp は min > max や NaN の範囲で panic するので先に確認する
+                        if x[1] <= x[2] {
+                            Ok(x[0].clamp(x[1], x[2]))
+                        } else {
+                            Err(PolishError::DomainError {
+                                operator: None,
+                                span: None,
+                            })
+                        }
+                    })
+                    .register("fma", 3, |x| Ok(x[0].mul_add(x[1], x[2])))
+                    .set_divisor("/", 1)
+                    .set_divisor("%", 1)
+                    .set_divisor("//", 1)
+                    .set_divisor("mod", 1)
+                    .set_variadic("+")
+                    .set_variadic("*")
+                    .set_variadic("min")
+                    .set_variadic("max");
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0.0
+            }
+
+            fn is_finite(&self) -> bool {
+                $t::is_finite(*self)
+            }
+
+            fn is_nan(&self) -> bool {
+                $t::is_nan(*self)
+            }
+        }
+    };
+}
+
+float_number!(f64);
+float_number!(f32);
+
+// 整数リテラルの文法。小数点や指数は持たない
+//
+// integer := sign? digit ( "_"? digit )*
+const INTEGER_PATTERN: &str = r"^[+-]?[0-9](?:_?[0-9])*$";
+
+fn integer_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(INTEGER_PATTERN).unwrap())
+}
+
+pub(crate) fn is_integer(word: &str) -> bool {
+    integer_regex().is_match(word)
+}
+
+// 整数や分数は 0 で割れないので、厳密モードでなくてもエラーにする
+pub(crate) fn nonzero<N: Number>(b: &N) -> Result<&N, PolishError> {
+    if b.is_zero() {
+        Err(PolishError::DivisionByZero {
+            operator: None,
+            span: None,
+        })
+    } else {
+        Ok(b)
+    }
+}
+
+// 累乗の結果のビット数の上限。これより大きな整数は作らない
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+// `bits` ビットの数の `exp` 乗が大きすぎれば DomainError にする。0 と ±1 の累乗はいつも小さい
+pub(crate) fn power_bits(bits: u64, exp: u64) -> Result<(), PolishError> {
+    if bits > 1 && bits.saturating_mul(exp) > MAX_POWER_BITS {
+        Err(PolishError::DomainError {
+            operator: None,
+            span: None,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+// 固定長の整数に収まらなければ IntegerOverflow にする
+fn checked(value: Option<i64>) -> Result<i64, PolishError> {
+    value.ok_or(PolishError::IntegerOverflow {
+        operator: None,
+        span: None,
+    })
+}
+
+// 負の指数は整数にならない
+fn exponent(exp: &impl ToPrimitive) -> Result<u32, PolishError> {
+    exp.to_u32().ok_or(PolishError::DomainError {
+        operator: None,
+        span: None,
+    })
+}
+
+/// a result that does not fit in `i64` fails with `IntegerOverflow`, and dividing by zero always
+/// fails with `DivisionByZero`. `/` rounds toward zero, `//` toward negative infinity, and `^` fails
+/// with `DomainError` for a negative exponent. there are no `sqrt`, `ln` or other real functions.
+impl Number for i64 {
+    fn parse_literal(token: &str) -> Option<Self> {
+        if is_integer(token) {
+            token.replace('_', "").parse().ok()
+        } else {
+            None
+        }
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register("+", 2, |x| checked(x[0].checked_add(x[1])))
+            .register("-", 2, |x| checked(x[0].checked_sub(x[1])))
+            .register("*", 2, |x| checked(x[0].checked_mul(x[1])))
+            .register("/", 2, |x| checked(x[0].checked_div(*nonzero(&x[1])?)))
+            // `% i64::MIN -1` は 0 なので、溢れることはない
+            .register("%", 2, |x| Ok(x[0].wrapping_rem(*nonzero(&x[1])?)))
+            .register("^", 2, |x| checked(x[0].checked_pow(exponent(&x[1])?)))
+            .register("//", 2, |x| {
+                let b = *nonzero(&x[1])?;
+                let q = checked(x[0].checked_div(b))?;
+                // 割り切れず符号が異なるときは負の無限大の方向に丸める
+                if x[0].wrapping_rem(b) != 0 && (x[0] < 0) != (b < 0) {
+                    Ok(q - 1)
+                } else {
+                    Ok(q)
+                }
+            })
+            .register("mod", 2, |x| Ok(x[0].wrapping_rem_euclid(*nonzero(&x[1])?)))
+            .register_binary("min", i64::min)
+            .register_binary("max", i64::max)
+            .register("neg", 1, |x| checked(x[0].checked_neg()))
+            .register("abs", 1, |x| checked(x[0].checked_abs()))
+            .register("clamp", 3, |x| {
+                if x[1] <= x[2] {
+                    Ok(x[0].clamp(x[1], x[2]))
+                } else {
+                    Err(PolishError::DomainError {
+                        operator: None,
+                        span: None,
+                    })
+                }
+            })
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+}
+
+/// integers of any size, so `^ 2 512` is exact. the operators are the same as `i64`,
+/// but they never overflow. `^` fails with `DomainError` when the exponent is negative
+/// or does not fit in `u32`, or when the result would have more than about a million bits.
+impl Number for BigInt {
+    fn parse_literal(token: &str) -> Option<Self> {
+        if is_integer(token) {
+            token.replace('_', "").parse().ok()
+        } else {
+            None
+        }
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register_binary("+", |a, b| a + b)
+            .register_binary("-", |a, b| a - b)
+            .register_binary("*", |a, b| a * b)
+            .register("/", 2, |x| Ok(&x[0] / nonzero(&x[1])?))
+            .register("%", 2, |x| Ok(&x[0] % nonzero(&x[1])?))
+            .register("^", 2, |x| {
+                let exp = exponent(&x[1])?;
+                power_bits(x[0].bits(), u64::from(exp))?;
+                Ok(x[0].pow(exp))
+            })
+            .register("//", 2, |x| Ok(x[0].div_floor(nonzero(&x[1])?)))
+            .register("mod", 2, |x| {
+                let b = nonzero(&x[1])?;
+                let r = &x[0] % b;
+                // 余りはいつも 0 以上にする
+                if r.is_negative() {
+                    Ok(r + b.abs())
+                } else {
+                    Ok(r)
+                }
+            })
+            .register_binary("min", Ord::min)
+            .register_binary("max", Ord::max)
+            .register_unary("neg", |a| -a)
+            .register_unary("abs", |a| a.abs())
+            .register("clamp", 3, |x| {
+                if x[1] <= x[2] {
+                    Ok(x[0].clone().clamp(x[1].clone(), x[2].clone()))
+                } else {
+                    Err(PolishError::DomainError {
+                        operator: None,
+                        span: None,
+                    })
+                }
+            })
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+}
+
+/// same as `pn`, but the exoression is evaluated as `N`, with the built-in operators of `N`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::pn_as;
+///
+/// assert_eq!(pn_as::<i64>("// -7 2"), Ok(-4));
+/// assert_eq!(pn_as::<f32>("* 1.5 2"), Ok(3.0));
+/// ```
+pub fn pn_as<N: Number>(expression: &str) -> Result<N, PolishError> {
+    Evaluator::<N>::default().pn(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Environment, Expr, ExprKind, Mode, Notation, Span};
+
+    #[test]
+    fn integer_test() {
+        let integer_overflow = |operator: &str, start: usize| {
+            Err(PolishError::IntegerOverflow {
+                operator: Some(operator.to_string()),
+                span: Some(Span::new(start, start + operator.len(), start / 2)),
+            })
+        };
+        let division_by_zero = |operator: &str| {
+            Err(PolishError::DivisionByZero {
+                operator: Some(operator.to_string()),
+                span: Some(Span::new(0, operator.len(), 0)),
+            })
+        };
+        let exoressions = [
+            ("+ 1 2", Ok(3)),
+            ("- 5 7", Ok(-2)),
+            ("/ 7 2", Ok(3)),
+            ("/ -7 2", Ok(-3)),
+            ("// -7 2", Ok(-4)),
+            ("// 7 2", Ok(3)),
+            ("% -7 2", Ok(-1)),
+            ("mod -7 2", Ok(1)),
+            ("^ 2 10", Ok(1024)),
+            ("^ 2 0", Ok(1)),
+            ("clamp 12 0 10", Ok(10)),
+            ("max abs -3 neg 5", Ok(3)),
+            ("+ 1_000 -1", Ok(999)),
+            ("% -9223372036854775808 -1", Ok(0)),
+            ("- 0 9223372036854775807", Ok(-9223372036854775807)),
+            // 以下エラーテスト
+            ("/ 1 0", division_by_zero("/")),
+            ("% 1 0", division_by_zero("%")),
+            ("// 1 0", division_by_zero("//")),
+            ("mod 1 0", division_by_zero("mod")),
+            (
+                "^ 2 -1",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 1.5",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1.5".to_string(),
+                    span: Span::new(4, 7, 2),
+                }),
+            ),
+            (
+                "sqrt 4",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(5, 6, 1)),
+                }),
+            ),
+            ("* 9223372036854775807 2", integer_overflow("*", 0)),
+            ("+ 9223372036854775807 1", integer_overflow("+", 0)),
+            ("- -9223372036854775808 1", integer_overflow("-", 0)),
+            ("/ -9223372036854775808 -1", integer_overflow("/", 0)),
+            ("// -9223372036854775808 -1", integer_overflow("//", 0)),
+            ("^ 2 63", integer_overflow("^", 0)),
+            ("+ 1 neg -9223372036854775808", integer_overflow("neg", 4)),
+            ("abs -9223372036854775808", integer_overflow("abs", 0)),
+            (
+                "+ 1 9223372036854775808",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "9223372036854775808".to_string(),
+                    span: Span::new(4, 23, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn_as::<i64>(exoression.0), exoression.1);
+        }
+    }
+
+    #[test]
+    fn bigint_test() {
+        let exoressions = [
+            ("^ 2 128", Ok("340282366920938463463374607431768211456")),
+            ("^ -1 4000000001", Ok("-1")),
+            ("* 9223372036854775807 2", Ok("18446744073709551614")),
+            ("+ 9223372036854775808 -1", Ok("9223372036854775807")),
+            (
+                "(* 1_000_000 1_000_000 1_000_000 1_000_000)",
+                Ok("1000000000000000000000000"),
+            ),
+            ("// -7 2", Ok("-4")),
+            ("/ -7 2", Ok("-3")),
+            ("% -7 2", Ok("-1")),
+            ("mod -7 2", Ok("1")),
+            ("mod 7 -2", Ok("1")),
+            ("max abs -3 neg 5", Ok("3")),
+            ("clamp 12 0 10", Ok("10")),
+            // 以下エラーテスト
+            (
+                "/ 1 0",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("/".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 2 -1",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 3 4000000000",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 1.5",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1.5".to_string(),
+                    span: Span::new(4, 7, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let ans = Evaluator::<BigInt>::default()
+                .notation(Notation::SExpression)
+                .pn(exoression.0);
+            assert_eq!(
+                ans.map(|ans| ans.to_string()),
+                exoression.1.map(str::to_string)
+            );
+        }
+    }
+
+    #[test]
+    fn float_test() {
+        assert_eq!(pn_as::<f32>("+ 0.1 0.2"), Ok(0.1f32 + 0.2f32));
+        assert_eq!(pn_as::<f64>("+ 0.1 0.2"), Ok(0.1 + 0.2));
+        assert_eq!(pn_as::<f32>("* 1e30 1e30"), Ok(f32::INFINITY));
+        assert!(matches!(
+            Evaluator::<f32>::default()
+                .mode(Mode::Strict)
+                .pn("* 1e30 1e30"),
+            Err(PolishError::Overflow { .. })
+        ));
+        assert_eq!(
+            pn_as::<f32>("sqrt 2"),
+            pn_as::<f64>("sqrt 2").map(|x| x as f32)
+        );
+    }
+
+    #[test]
+    fn generic_test() {
+        let registry = OperatorRegistry::<i64>::new();
+        let mut env = Environment::new();
+        env.set("x", 10);
+        let expr = Notation::Infix
+            .parse(&registry, "(x + 2) * 3 // 4")
+            .unwrap();
+        assert_eq!(expr.to_string(), "// * + x 2 3 4");
+        assert_eq!(expr.eval_in(&env), Ok(9));
+        assert_eq!(
+            Evaluator::default()
+                .env(&env)
+                .notation(Notation::Postfix)
+                .pn("x 3 -"),
+            Ok(7)
+        );
+
+        let node: Expr<i64> = Expr::operator(
+            "-",
+            vec![
+                Expr::number(1),
+                Expr {
+                    kind: ExprKind::Variable("x".to_string()),
+                    span: None,
+                },
+            ],
+        );
+        assert_eq!(node.eval_with(&registry, &env), Ok(-9));
+        assert_eq!(node.to_infix(), "1 - x");
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{Expr, Number, PolishError, Span};
+
+/// Hooks that `Evaluator::eval_observed` calls while it calculates an expression.
+///
+/// the evaluator reads the tokens from right to left with an operand stack,
+/// in the same order as the original `pn` loop (from left to right for `Notation::Postfix` and `Notation::Infix`):
+///
+/// 1. `on_token` when it reads a number, variable or operator.
+/// 2. `on_apply` when an operator has taken its operands off the stack and produced a result.
+/// 3. `on_push` when a value is pushed, with the stack after the push.
+/// 4. `on_error` once, when evaluation fails.
+///
+/// every method does nothing by default, so implement only the ones you need.
+/// `()` is an observer that ignores everything.
+/// `N` is the `Number` type being evaluated, `f64` by default.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{parse, EvalObserver, Evaluator};
+///
+/// #[derive(Default)]
+/// struct MaxDepth(usize);
+///
+/// impl EvalObserver for MaxDepth {
+///     fn on_push(&mut self, _value: f64, stack: &[f64]) {
+///         self.0 = self.0.max(stack.len());
+///     }
+/// }
+///
+/// let mut depth = MaxDepth::default();
+/// let expr = parse("* + 1 2 3").unwrap();
+/// assert_eq!(Evaluator::new().eval_observed(&expr, &mut depth), Ok(9.0));
+/// assert_eq!(depth.0, 3);
+/// ```
+pub trait EvalObserver<N = f64> {
+    /// `expr` is the node of the token that was read.
+    fn on_token(&mut self, _expr: &Expr<N>) {}
+
+    /// `value` was pushed, and `stack` is the operand stack after the push, with the top last.
+    fn on_push(&mut self, _value: N, _stack: &[N]) {}
+
+    /// `operator` was applied to `operands`, in the order they appear in the exoression.
+    fn on_apply(&mut self, _operator: &str, _operands: &[N], _result: N) {}
+
+    /// evaluation failed with `error`.
+    fn on_error(&mut self, _error: &PolishError) {}
+}
+
+impl<N> EvalObserver<N> for () {}
+
+/// One event recorded by `TraceRecorder`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent<N = f64> {
+    Token {
+        text: String,
+        span: Option<Span>,
+    },
+    Push {
+        value: N,
+        stack: Vec<N>,
+    },
+    Apply {
+        operator: String,
+        operands: Vec<N>,
+        result: N,
+    },
+    Error(PolishError),
+}
+
+/// An `EvalObserver` that records every event, so you can look at the evaluation step by step.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{parse, Evaluator, TraceEvent, TraceRecorder};
+///
+/// let mut recorder = TraceRecorder::new();
+/// let expr = parse("- 5 2").unwrap();
+/// Evaluator::new().eval_observed(&expr, &mut recorder).unwrap();
+///
+/// assert_eq!(
+///     recorder.events().last(),
+///     Some(&TraceEvent::Push { value: 3.0, stack: vec![3.0] })
+/// );
+/// for event in recorder.events() {
+///     println!("{:?}", event);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecorder<N = f64> {
+    events: Vec<TraceEvent<N>>,
+}
+
+impl TraceRecorder {
+    /// create a recorder of `f64` evaluation without any events.
+    /// use `TraceRecorder::<N>::default()` for another `Number` type.
+    pub fn new() -> Self {
+        TraceRecorder::default()
+    }
+}
+
+impl<N> TraceRecorder<N> {
+    /// the events in the order they happened.
+    pub fn events(&self) -> &[TraceEvent<N>] {
+        &self.events
+    }
+
+    /// take the recorded events out, leaving the recorder empty.
+    pub fn take(&mut self) -> Vec<TraceEvent<N>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<N> Default for TraceRecorder<N> {
+    fn default() -> Self {
+        TraceRecorder { events: vec![] }
+    }
+}
+
+impl<N: Number> EvalObserver<N> for TraceRecorder<N> {
+    fn on_token(&mut self, expr: &Expr<N>) {
+        self.events.push(TraceEvent::Token {
+            text: expr.token(),
+            span: expr.span,
+        });
+    }
+
+    fn on_push(&mut self, value: N, stack: &[N]) {
+        self.events.push(TraceEvent::Push {
+            value,
+            stack: stack.to_vec(),
+        });
+    }
+
+    fn on_apply(&mut self, operator: &str, operands: &[N], result: N) {
+        self.events.push(TraceEvent::Apply {
+            operator: operator.to_string(),
+            operands: operands.to_vec(),
+            result,
+        });
+    }
+
+    fn on_error(&mut self, error: &PolishError) {
+        self.events.push(TraceEvent::Error(error.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Evaluator, Mode};
+
+    fn token(text: &str, start: usize, end: usize, index: usize) -> TraceEvent {
+        TraceEvent::Token {
+            text: text.to_string(),
+            span: Some(Span::new(start, end, index)),
+        }
+    }
+
+    fn push(value: f64, stack: &[f64]) -> TraceEvent {
+        TraceEvent::Push {
+            value,
+            stack: stack.to_vec(),
+        }
+    }
+
+    #[test]
+    fn trace_test() {
+        let mut recorder = TraceRecorder::new();
+        let expr = parse("* + 5 1 2").unwrap();
+        assert_eq!(
+            Evaluator::new().eval_observed(&expr, &mut recorder),
+            Ok(12.0)
+        );
+        assert_eq!(
+            recorder.take(),
+            vec![
+                token("2", 8, 9, 4),
+                push(2.0, &[2.0]),
+                token("1", 6, 7, 3),
+                push(1.0, &[2.0, 1.0]),
+                token("5", 4, 5, 2),
+                push(5.0, &[2.0, 1.0, 5.0]),
+                token("+", 2, 3, 1),
+                TraceEvent::Apply {
+                    operator: "+".to_string(),
+                    operands: vec![5.0, 1.0],
+                    result: 6.0,
+                },
+                push(6.0, &[2.0, 6.0]),
+                token("*", 0, 1, 0),
+                TraceEvent::Apply {
+                    operator: "*".to_string(),
+                    operands: vec![6.0, 2.0],
+                    result: 12.0,
+                },
+                push(12.0, &[12.0]),
+            ]
+        );
+        assert!(recorder.events().is_empty());
+    }
+
+    #[test]
+    fn error_trace_test() {
+        let mut recorder = TraceRecorder::new();
+        let expr = parse("+ 1 / 1 0").unwrap();
+        let error = Evaluator::new()
+            .mode(Mode::Strict)
+            .eval_observed(&expr, &mut recorder)
+            .unwrap_err();
+        assert_eq!(
+            recorder.events(),
+            &[
+                token("0", 8, 9, 4),
+                push(0.0, &[0.0]),
+                token("1", 6, 7, 3),
+                push(1.0, &[0.0, 1.0]),
+                token("/", 4, 5, 2),
+                TraceEvent::Error(error),
+            ]
+        );
+    }
+}
@@ -0,0 +1,685 @@
+use crate::{Number, OperatorRegistry, PolishError};
+use std::fmt;
+
+// 基本単位の記号。次元はこの順に指数を並べる
+const BASE_UNITS: [&str; 7] = ["kg", "m", "s", "A", "K", "mol", "cd"];
+
+type Dimension = [i32; 7];
+
+const DIMENSIONLESS: Dimension = [0; 7];
+
+// 単位の記号、SI 単位での大きさ、次元
+const UNITS: [(&str, f64, Dimension); 16] = [
+    ("m", 1.0, [0, 1, 0, 0, 0, 0, 0]),
+    ("g", 1e-3, [1, 0, 0, 0, 0, 0, 0]),
+    ("s", 1.0, [0, 0, 1, 0, 0, 0, 0]),
+    ("A", 1.0, [0, 0, 0, 1, 0, 0, 0]),
+    ("K", 1.0, [0, 0, 0, 0, 1, 0, 0]),
+    ("mol", 1.0, [0, 0, 0, 0, 0, 1, 0]),
+    ("cd", 1.0, [0, 0, 0, 0, 0, 0, 1]),
+    ("Hz", 1.0, [0, 0, -1, 0, 0, 0, 0]),
+    ("N", 1.0, [1, 1, -2, 0, 0, 0, 0]),
+    ("Pa", 1.0, [1, -1, -2, 0, 0, 0, 0]),
+    ("J", 1.0, [1, 2, -2, 0, 0, 0, 0]),
+    ("W", 1.0, [1, 2, -3, 0, 0, 0, 0]),
+    ("C", 1.0, [0, 0, 1, 1, 0, 0, 0]),
+    ("V", 1.0, [1, 2, -3, -1, 0, 0, 0]),
+    ("L", 1e-3, [0, 3, 0, 0, 0, 0, 0]),
+    ("h", 3600.0, [0, 0, 1, 0, 0, 0, 0]),
+];
+
+const PREFIXES: [(&str, f64); 9] = [
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("µ", 1e-6),
+    ("n", 1e-9),
+];
+
+/// A number with a physical unit, such as `5m`, `2s` or `9.81m/s^2`.
+///
+/// a unit is written right after the number, without spaces. it is made of the SI base units
+/// `m`, `g`, `s`, `A`, `K`, `mol` and `cd`, the derived units `Hz`, `N`, `Pa`, `J`, `W`, `C`, `V`
+/// and `L`, and the hour `h`, with one of the prefixes `T`, `G`, `M`, `k`, `c`, `m`, `u` (or `µ`)
+/// and `n`. units are joined with `*` and `/`, and `^` gives an integer power, as in `kg*m/s^2`.
+/// a unit without a number, such as `km/h`, is one of that unit, so it cannot be a variable name.
+///
+/// `*` and `/` multiply the dimensions, and `+`, `-`, `min`, `max` and the remainders need operands
+/// of the same dimension, or fail with `DimensionMismatch`. the result of `+` is in the unit of
+/// the first operand, and a product is in SI units unless an operand has no unit.
+/// `to` converts the first operand to the unit of the second, so `to x km/h` is `x` in `km/h`.
+/// `ln`, `exp`, `sin` and the other functions take numbers without units,
+/// and `^` takes an exponent without units that gives integer powers of the base units.
+/// an exponent of a base unit that does not fit in `i32` fails with `IntegerOverflow`.
+///
+/// a `Quantity` prints as `5 m/s`, with a space before the unit.
+/// two quantities are equal when they have the same dimension and the same value in SI units.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, PolishError, Quantity};
+///
+/// let ans = pn_as::<Quantity>("/ 10m 2s").unwrap();
+/// assert_eq!(ans.to_string(), "5 m/s");
+/// assert_eq!(pn_as::<Quantity>("to / 10m 2s km/h").unwrap().to_string(), "18 km/h");
+/// assert_eq!(pn_as::<Quantity>("+ 1km 500m").unwrap().to_string(), "1.5 km");
+/// assert!(matches!(
+///     pn_as::<Quantity>("+ 1m 1s"),
+///     Err(PolishError::DimensionMismatch { .. })
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Quantity {
+    // SI 単位での値
+    value: f64,
+    dimension: Dimension,
+    // 表示に使う単位。None なら SI の基本単位で表す
+    unit: Option<Unit>,
+}
+
+#[derive(Debug, Clone)]
+struct Unit {
+    name: String,
+    // SI 単位での値 = 表示する値 * numer / denom
+    numer: f64,
+    denom: f64,
+}
+
+impl Quantity {
+    /// `value` in `unit`, or `None` when `unit` is not a unit.
+    pub fn new(value: f64, unit: &str) -> Option<Self> {
+        let unit_quantity = Quantity::parse_unit(unit)?;
+        let shown = unit_quantity.unit.as_ref()?;
+        Some(Quantity {
+            value: value * shown.numer / shown.denom,
+            ..unit_quantity
+        })
+    }
+
+    /// a number without a unit.
+    pub fn dimensionless(value: f64) -> Self {
+        Quantity {
+            value,
+            dimension: DIMENSIONLESS,
+            unit: None,
+        }
+    }
+
+    /// the value in the unit this quantity prints with.
+    pub fn value(&self) -> f64 {
+        match &self.unit {
+            Some(unit) => self.value * unit.denom / unit.numer,
+            None => self.value,
+        }
+    }
+
+    /// the unit this quantity prints with, empty when it has no dimension.
+    pub fn unit(&self) -> String {
+        match &self.unit {
+            Some(unit) => unit.name.clone(),
+            None => si_unit(&self.dimension),
+        }
+    }
+
+    /// this quantity in `unit`, or `None` when `unit` is not a unit of the same dimension.
+    pub fn to(&self, unit: &str) -> Option<Quantity> {
+        self.convert(&Quantity::parse_unit(unit)?).ok()
+    }
+
+    fn convert(&self, target: &Quantity) -> Result<Quantity, PolishError> {
+        target.same_dimension(self)?;
+        Ok(Quantity {
+            value: self.value,
+            dimension: self.dimension,
+            unit: target.unit.clone(),
+        })
+    }
+
+    fn same_dimension(&self, other: &Quantity) -> Result<(), PolishError> {
+        if self.dimension == other.dimension {
+            Ok(())
+        } else {
+            Err(mismatch(&self.dimension, &other.dimension))
+        }
+    }
+
+    fn without_dimension(&self) -> Result<f64, PolishError> {
+        Quantity::dimensionless(0.0)
+            .same_dimension(self)
+            .map(|_| self.value)
+    }
+
+    // 値だけ変えて、同じ単位のまま返す
+    fn with_value(&self, value: f64) -> Quantity {
+        Quantity {
+            value,
+            ..self.clone()
+        }
+    }
+
+    // 表示する単位で丸める
+    fn round_shown(&self, f: fn(f64) -> f64) -> Quantity {
+        match &self.unit {
+            Some(unit) => self.with_value(f(self.value()) * unit.numer / unit.denom),
+            None => self.with_value(f(self.value)),
+        }
+    }
+
+    // `km/h` や `kg*m/s^2` のような単位を、その単位 1 つ分の量として読む
+    fn parse_unit(text: &str) -> Option<Quantity> {
+        let mut numer = 1.0;
+        let mut denom = 1.0;
+        let mut dimension = DIMENSIONLESS;
+        let mut sign = 1;
+        let mut rest = text;
+        loop {
+            let end = rest.find(['*', '/']).unwrap_or(rest.len());
+            let (symbol, power) = match rest[..end].split_once('^') {
+                Some((symbol, power)) => (symbol, power.parse::<i32>().ok()?),
+                None => (&rest[..end], 1),
+            };
+            let (scale, unit_dimension) = lookup(symbol)?;
+            // 指数が i32 に収まらない単位は読めない
+            let power = power.checked_mul(sign)?;
+            if power > 0 {
+                numer *= scale.powi(power);
+            } else {
+                denom *= scale.powi(power.checked_neg()?);
+            }
+            for (d, u) in dimension.iter_mut().zip(unit_dimension) {
+                *d = u.checked_mul(power).and_then(|u| d.checked_add(u))?;
+            }
+            if end == rest.len() {
+                break;
+            }
+            sign = if rest[end..].starts_with('/') { -1 } else { 1 };
+            rest = &rest[end + 1..];
+        }
+        Some(Quantity {
+            value: numer / denom,
+            dimension,
+            unit: Some(Unit {
+                name: text.to_string(),
+                numer,
+                denom,
+            }),
+        })
+    }
+
+    // 掛け算や割り算の結果の単位。単位のない相手となら、もう一方の単位のまま
+    fn product(&self, other: &Quantity, value: f64, dimension: Dimension) -> Quantity {
+        let unit = if other.dimension == DIMENSIONLESS && other.unit.is_none() {
+            self.unit.clone()
+        } else {
+            None
+        };
+        Quantity {
+            value,
+            dimension,
+            unit: unit.filter(|_| dimension != DIMENSIONLESS),
+        }
+    }
+}
+
+// 接頭辞の付いた単位も探す
+fn lookup(symbol: &str) -> Option<(f64, Dimension)> {
+    let find = |symbol: &str| {
+        UNITS
+            .iter()
+            .find(|unit| unit.0 == symbol)
+            .map(|unit| (unit.1, unit.2))
+    };
+    find(symbol).or_else(|| {
+        PREFIXES.iter().find_map(|(prefix, factor)| {
+            let (scale, dimension) = find(symbol.strip_prefix(prefix)?)?;
+            Some((factor * scale, dimension))
+        })
+    })
+}
+
+// 次元を SI の基本単位で表す。分母だけのときは負の指数にする
+fn si_unit(dimension: &Dimension) -> String {
+    let factor = |(symbol, power): (&str, i32)| match power {
+        1 => symbol.to_string(),
+        power => format!("{}^{}", symbol, power),
+    };
+    let numer: Vec<String> = BASE_UNITS
+        .iter()
+        .zip(dimension)
+        .filter(|(_, &power)| power > 0)
+        .map(|(symbol, &power)| factor((symbol, power)))
+        .collect();
+    let denom = BASE_UNITS
+        .iter()
+        .zip(dimension)
+        .filter(|(_, &power)| power < 0);
+    if numer.is_empty() {
+        denom
+            .map(|(symbol, &power)| factor((symbol, power)))
+            .collect::<Vec<_>>()
+            .join("*")
+    } else {
+        denom.fold(numer.join("*"), |unit, (symbol, &power)| {
+            format!("{}/{}", unit, factor((symbol, -power)))
+        })
+    }
+}
+
+fn mismatch(expected: &Dimension, found: &Dimension) -> PolishError {
+    let name = |dimension: &Dimension| match si_unit(dimension) {
+        unit if unit.is_empty() => "1".to_string(),
+        unit => unit,
+    };
+    PolishError::DimensionMismatch {
+        expected: name(expected),
+        found: name(found),
+        operator: None,
+        span: None,
+    }
+}
+
+/// prints the value and the unit with a space between, such as `5 m/s`.
+/// a precision, such as `{:.2}`, is used for the value.
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*}", p, self.value())?,
+            None => write!(f, "{}", self.value())?,
+        }
+        match self.unit() {
+            unit if unit.is_empty() => Ok(()),
+            unit => write!(f, " {}", unit),
+        }
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.dimension == other.dimension && self.value == other.value
+    }
+}
+
+// 2 つの量の次元を足し合わせる、または引く。指数が i32 に収まらなければ IntegerOverflow にする
+fn add_dimensions(a: &Dimension, b: &Dimension, sign: i32) -> Result<Dimension, PolishError> {
+    let mut dimension = *a;
+    for (d, b) in dimension.iter_mut().zip(b) {
+        *d = b.checked_mul(sign).and_then(|b| d.checked_add(b)).ok_or(
+            PolishError::IntegerOverflow {
+                operator: None,
+                span: None,
+            },
+        )?;
+    }
+    Ok(dimension)
+}
+
+// 同じ次元の 2 つの量から、1 つ目の単位の量を作る
+fn same(
+    f: fn(f64, f64) -> f64,
+) -> impl Fn(&[Quantity]) -> Result<Quantity, PolishError> + Send + Sync + 'static {
+    move |x| {
+        x[0].same_dimension(&x[1])?;
+        Ok(x[0].with_value(f(x[0].value, x[1].value)))
+    }
+}
+
+// 単位のない数を受け取る関数
+fn function(
+    f: fn(f64) -> f64,
+) -> impl Fn(&[Quantity]) -> Result<Quantity, PolishError> + Send + Sync + 'static {
+    move |x| Ok(Quantity::dimensionless(f(x[0].without_dimension()?)))
+}
+
+impl Number for Quantity {
+    fn parse_literal(token: &str) -> Option<Self> {
+        if let Some(value) = f64::parse_literal(token) {
+            return Some(Quantity::dimensionless(value));
+        }
+        // 数と単位の境目を探す。`2e3m` の `e` のように、単位に見えても数の一部のことがある
+        token
+            .char_indices()
+            .filter(|(_, c)| c.is_alphabetic())
+            .find_map(|(i, _)| match i {
+                0 => Quantity::parse_unit(token),
+                i => Quantity::new(f64::parse_literal(&token[..i])?, &token[i..]),
+            })
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        registry
+            .register("+", 2, same(|a, b| a + b))
+            .register("-", 2, same(|a, b| a - b))
+            .register("*", 2, |x| {
+                let (a, b) = (&x[0], &x[1]);
+                let dimension = add_dimensions(&a.dimension, &b.dimension, 1)?;
+                Ok(if a.dimension == DIMENSIONLESS && a.unit.is_none() {
+                    b.product(a, a.value * b.value, dimension)
+                } else {
+                    a.product(b, a.value * b.value, dimension)
+                })
+            })
+            .register("/", 2, |x| {
+                let dimension = add_dimensions(&x[0].dimension, &x[1].dimension, -1)?;
+                Ok(x[0].product(&x[1], x[0].value / x[1].value, dimension))
+            })
+            .register("%", 2, same(|a, b| a % b))
+            .register("mod", 2, same(f64::rem_euclid))
+            .register("//", 2, |x| {
+                x[0].same_dimension(&x[1])?;
+                Ok(Quantity::dimensionless((x[0].value / x[1].value).floor()))
+            })
+            .register("^", 2, |x| {
+                let power = x[1].without_dimension()?;
+                let mut dimension = DIMENSIONLESS;
+                for (d, base) in dimension.iter_mut().zip(x[0].dimension) {
+                    let d_power = base as f64 * power;
+                    if d_power.fract() != 0.0 {
+                        return Err(PolishError::DomainError {
+                            operator: None,
+                            span: None,
+                        });
+                    }
+                    if d_power.abs() > i32::MAX as f64 {
+                        return Err(PolishError::IntegerOverflow {
+                            operator: None,
+                            span: None,
+                        });
+                    }
+                    *d = d_power as i32;
+                }
+                Ok(Quantity {
+                    value: x[0].value.powf(power),
+                    dimension,
+                    unit: None,
+                })
+            })
+            .register("min", 2, |x| {
+                x[0].same_dimension(&x[1])?;
+                Ok(if x[1].value < x[0].value {
+                    &x[1]
+                } else {
+                    &x[0]
+                }
+                .clone())
+            })
+            .register("max", 2, |x| {
+                x[0].same_dimension(&x[1])?;
+                Ok(if x[1].value > x[0].value {
+                    &x[1]
+                } else {
+                    &x[0]
+                }
+                .clone())
+            })
+            .register("to", 2, |x| x[0].convert(&x[1]))
+            .register_unary("neg", |a| a.with_value(-a.value))
+            .register_unary("abs", |a| a.with_value(a.value.abs()))
+            .register("sqrt", 1, |x| {
+                let mut dimension = DIMENSIONLESS;
+                for (d, base) in dimension.iter_mut().zip(x[0].dimension) {
+                    if base % 2 != 0 {
+                        return Err(PolishError::DomainError {
+                            operator: None,
+                            span: None,
+                        });
+                    }
+                    *d = base / 2;
+                }
+                Ok(Quantity {
+                    value: x[0].value.sqrt(),
+                    dimension,
+                    unit: None,
+                })
+            })
+            .register("ln", 1, function(f64::ln))
+            .register("log", 1, function(f64::log10))
+            .register("exp", 1, function(f64::exp))
+            .register("sin", 1, function(f64::sin))
+            .register("cos", 1, function(f64::cos))
+            .register("tan", 1, function(f64::tan))
+            .register_unary("floor", |a| a.round_shown(f64::floor))
+            .register_unary("ceil", |a| a.round_shown(f64::ceil))
+            .register_unary("round", |a| a.round_shown(f64::round))
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value == 0.0
+    }
+
+    fn is_finite(&self) -> bool {
+        self.value.is_finite()
+    }
+
+    fn is_nan(&self) -> bool {
+        self.value.is_nan()
+    }
+
+    // 字句として読み直せるよう、数と単位の間に空白を入れない
+    fn token(&self) -> String {
+        format!("{}{}", self.value(), self.unit())
+    }
+
+    // `10m/s` のように数のすぐ後に続く単位も含める。単位として読めるところまでで区切るので、
+    // `10m/2s` は `10m`、`/`、`2s` になる
+    fn scan_literal(text: &str) -> Option<usize> {
+        let number = f64::scan_literal(text)?;
+        let rest = &text[number..];
+        if !rest.starts_with(char::is_alphabetic) {
+            return Some(number);
+        }
+        // 単位に使える文字の続くところまで。`-` は `m^-1` のように `^` の後だけ
+        let mut end = number
+            + rest
+                .char_indices()
+                .find(|&(i, c)| {
+                    !(c.is_alphanumeric()
+                        || "_^*/".contains(c)
+                        || c == '-' && rest[..i].ends_with('^'))
+                })
+                .map_or(rest.len(), |(i, _)| i);
+        while end > number && Quantity::parse_literal(&text[..end]).is_none() {
+            end = text[number..end]
+                .rfind(['*', '/'])
+                .map_or(number, |i| number + i);
+        }
+        Some(end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Evaluator, Mode, Notation, Span};
+
+    #[test]
+    fn quantity_test() {
+        let exoressions = [
+            ("/ 10m 2s", Ok("5 m/s")),
+            ("to / 10m 2s km/h", Ok("18 km/h")),
+            ("to 1.5h s", Ok("5400 s")),
+            ("+ 1km 500m", Ok("1.5 km")),
+            ("- 1m 20cm", Ok("0.8 m")),
+            ("* 2 3kg", Ok("6 kg")),
+            ("* 3kg 2m/s^2", Ok("6 kg*m/s^2")),
+            ("to * 3kg 2m/s^2 N", Ok("6 N")),
+            ("/ 1 4s", Ok("0.25 s^-1")),
+            ("/ 6km 3km", Ok("2")),
+            ("^ 3m 2", Ok("9 m^2")),
+            ("sqrt 16m^2", Ok("4 m")),
+            ("min 1km 900m", Ok("900 m")),
+            ("// 7m 2m", Ok("3")),
+            ("% 7m 2m", Ok("1 m")),
+            ("floor 1.5km", Ok("1 km")),
+            ("neg 2e3mm", Ok("-2000 mm")),
+            ("+ 1 2", Ok("3")),
+            ("exp 0", Ok("1")),
+            ("to 1kW*h J", Ok("3600000 J")),
+            ("(+ 1m 2m 3m)", Ok("6 m")),
+            // 以下エラーテスト
+            (
+                "+ 1m 1s",
+                Err(PolishError::DimensionMismatch {
+                    expected: "m".to_string(),
+                    found: "s".to_string(),
+                    operator: Some("+".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "to 5m/s kg",
+                Err(PolishError::DimensionMismatch {
+                    expected: "kg".to_string(),
+                    found: "m/s".to_string(),
+                    operator: Some("to".to_string()),
+                    span: Some(Span::new(0, 2, 0)),
+                }),
+            ),
+            (
+                "ln 2m",
+                Err(PolishError::DimensionMismatch {
+                    expected: "1".to_string(),
+                    found: "m".to_string(),
+                    operator: Some("ln".to_string()),
+                    span: Some(Span::new(0, 2, 0)),
+                }),
+            ),
+            (
+                "sqrt 2m",
+                Err(PolishError::DomainError {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "* m^2147483647 m",
+                Err(PolishError::IntegerOverflow {
+                    operator: Some("*".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "/ m^-2147483647 m^2",
+                Err(PolishError::IntegerOverflow {
+                    operator: Some("/".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ m^2 2000000000",
+                Err(PolishError::IntegerOverflow {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 m^2147483647*m",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "m^2147483647*m".to_string(),
+                    span: Span::new(4, 18, 2),
+                }),
+            ),
+            (
+                "/ 1 m^-2147483648",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "m^-2147483648".to_string(),
+                    span: Span::new(4, 17, 2),
+                }),
+            ),
+            (
+                "+ 1 2furlong",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "2furlong".to_string(),
+                    span: Span::new(4, 12, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let ans = Evaluator::<Quantity>::default()
+                .notation(Notation::SExpression)
+                .pn(exoression.0)
+                .map(|ans| ans.to_string());
+            assert_eq!(ans, exoression.1.map(str::to_string));
+        }
+
+        assert!(matches!(
+            Evaluator::<Quantity>::default()
+                .mode(Mode::Strict)
+                .pn("/ 1m 0s"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn literal_test() {
+        let exoressions = [
+            ("5m", Quantity::new(5.0, "m")),
+            ("2e3m", Quantity::new(2000.0, "m")),
+            ("-3kg", Quantity::new(-3.0, "kg")),
+            ("9.81m/s^2", Quantity::new(9.81, "m/s^2")),
+            ("km/h", Quantity::new(1.0, "km/h")),
+            ("5µs", Quantity::new(5.0, "us")),
+            ("1_000", Some(Quantity::dimensionless(1000.0))),
+            ("mod", None),
+            ("x", None),
+            ("5m/", None),
+            ("5m^x", None),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(Quantity::parse_literal(exoression.0), exoression.1);
+        }
+
+        let speed = Quantity::new(5.0, "m/s").unwrap();
+        assert_eq!(speed.to("km/h").map(|q| q.value()), Some(18.0));
+        assert_eq!(speed.to("kg"), None);
+        assert_eq!(format!("{:.2}", speed), "5.00 m/s");
+        assert_eq!(Quantity::new(1.0, "furlong"), None);
+    }
+
+    #[test]
+    fn token_test() {
+        let registry = OperatorRegistry::<Quantity>::new();
+        let expr = Notation::Prefix.parse(&registry, "/ 10m 2s").unwrap();
+        assert_eq!(expr.to_string(), "/ 10m 2s");
+        let expr = Notation::Infix.parse(&registry, "10m / 2s").unwrap();
+        assert_eq!(
+            expr.eval().map(|ans| ans.to_string()),
+            Ok("5 m/s".to_string())
+        );
+        assert_eq!(
+            Notation::Prefix
+                .parse(&registry, &expr.to_string())
+                .and_then(|prefix| prefix.eval()),
+            expr.eval()
+        );
+
+        // 数のすぐ後の単位は、単位として読めるところまでがリテラル
+        let exoressions = [
+            ("10m/2s", "5 m/s"),
+            ("2 * 10m/s", "20 m/s"),
+            ("1km/h*2", "2 km/h"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let expr = Notation::Infix.parse(&registry, exoression.0).unwrap();
+            assert_eq!(
+                expr.eval().map(|ans| ans.to_string()),
+                Ok(exoression.1.to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use crate::number::{nonzero, power_bits};
+use crate::{is_number, Number, OperatorRegistry, PolishError};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::fmt;
+
+// 指数の大きすぎるリテラルは巨大な整数になるので読まない
+pub(crate) const MAX_SCALE: u32 = 10_000;
+
+/// An exact fraction of big integers.
+///
+/// evaluating as `Rational` keeps the numerator and the denominator, so there is no rounding error.
+/// number literals such as `0.1` or `2.5e-3` are read exactly.
+/// operators whose result is not rational, such as `sqrt 2` or `^ 2 0.5`, fail with `DomainError`,
+/// and dividing by zero always fails with `DivisionByZero`.
+/// `^` also fails with `DomainError` when the result would have more than about a million bits.
+/// there are no `ln`, `sin` or other real functions.
+///
+/// a `Rational` prints as a fraction such as `1/2`, or as a decimal when a precision is given.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_as, Rational};
+///
+/// let ans = pn_as::<Rational>("+ / 1 3 / 1 6").unwrap();
+/// assert_eq!(ans, Rational::new(1, 2).unwrap());
+/// assert_eq!(ans.to_string(), "1/2");
+/// assert_eq!(format!("{:.3}", ans), "0.500");
+///
+/// let third = pn_as::<Rational>("/ 1 3").unwrap();
+/// assert_eq!(format!("{:.4}", third), "0.3333");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rational(BigRational);
+
+impl Rational {
+    /// `numer / denom`, or `None` when `denom` is zero.
+    pub fn new(numer: impl Into<BigInt>, denom: impl Into<BigInt>) -> Option<Self> {
+        let denom = denom.into();
+        if Zero::is_zero(&denom) {
+            None
+        } else {
+            Some(Rational(BigRational::new(numer.into(), denom)))
+        }
+    }
+
+    /// the numerator. it has the sign of the fraction.
+    pub fn numer(&self) -> &BigInt {
+        self.0.numer()
+    }
+
+    /// the denominator. it is always positive.
+    pub fn denom(&self) -> &BigInt {
+        self.0.denom()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.0.is_integer()
+    }
+
+    /// the nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    /// the exact value of `x`, or `None` when it is infinite or NaN.
+    pub fn from_f64(x: f64) -> Option<Self> {
+        BigRational::from_float(x).map(Rational)
+    }
+
+    // 正確な q 乗根。有理数でなければ None
+    fn root(&self, q: u32) -> Option<Self> {
+        if self.0.is_negative() && q.is_multiple_of(2) {
+            return None;
+        }
+        let n = self.numer().nth_root(q);
+        let d = self.denom().nth_root(q);
+        let root = BigRational::new(n, d);
+        (root.pow(i32::try_from(q).ok()?) == self.0).then_some(Rational(root))
+    }
+
+    // 分母 q の指数なら q 乗根を取ってから p 乗する
+    fn pow(&self, exponent: &Self) -> Result<Self, PolishError> {
+        let domain_error = PolishError::DomainError {
+            operator: None,
+            span: None,
+        };
+        let p = exponent.numer().to_i32().ok_or(domain_error.clone())?;
+        let q = exponent.denom().to_u32().ok_or(domain_error.clone())?;
+        let base = if q == 1 {
+            self.clone()
+        } else {
+            self.root(q).ok_or(domain_error)?
+        };
+        // 分子と分母のうち大きいほうの桁数から、結果の大きさを見積もる
+        let bits = base.numer().bits().max(base.denom().bits());
+        power_bits(bits, u64::from(p.unsigned_abs()))?;
+        if base.0.is_zero() && p < 0 {
+            return Err(PolishError::DivisionByZero {
+                operator: None,
+                span: None,
+            });
+        }
+        Ok(Rational(base.0.pow(p)))
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Rational(BigRational::from_integer(n.into()))
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(n: BigInt) -> Self {
+        Rational(BigRational::from_integer(n))
+    }
+}
+
+/// without a precision it prints a fraction, `3/4`, or an integer, `3`.
+/// with a precision, `{:.2}`, it prints a decimal rounded half away from zero, `0.75`.
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(precision) = f.precision() else {
+            return write!(f, "{}", self.0);
+        };
+        let scale = BigInt::from(10).pow(precision as u32);
+        let digits = (&self.0 * BigRational::from_integer(scale.clone()))
+            .round()
+            .to_integer();
+        let sign = if digits.is_negative() { "-" } else { "" };
+        let digits = digits.abs();
+        let int = &digits / &scale;
+        if precision == 0 {
+            write!(f, "{}{}", sign, int)
+        } else {
+            let frac = &digits % &scale;
+            write!(f, "{}{}.{:0>width$}", sign, int, frac, width = precision)
+        }
+    }
+}
+
+impl Number for Rational {
+    fn parse_literal(token: &str) -> Option<Self> {
+        if !is_number(token) {
+            return None;
+        }
+        // `12.5e-3` を 125 × 10^(-3-1) として読む。inf と nan はここで弾かれる
+        let token = token.replace('_', "");
+        let (mantissa, exponent) = match token.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (token.as_str(), 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits: BigInt = format!("{}{}", int, frac).parse().ok()?;
+        let scale = exponent.checked_sub(frac.len() as i64)?;
+        let power = u32::try_from(scale.unsigned_abs())
+            .ok()
+            .filter(|&power| power <= MAX_SCALE)?;
+        let power = BigInt::from(10).pow(power);
+        if scale < 0 {
+            Rational::new(digits, power)
+        } else {
+            Some(Rational::from(digits * power))
+        }
+    }
+
+    fn builtins(registry: &mut OperatorRegistry<Self>) {
+        let domain_error = || PolishError::DomainError {
+            operator: None,
+            span: None,
+        };
+        registry
+            .register_binary("+", |a, b| Rational(a.0 + b.0))
+            .register_binary("-", |a, b| Rational(a.0 - b.0))
+            .register_binary("*", |a, b| Rational(a.0 * b.0))
+            .register("/", 2, |x| Ok(Rational(&x[0].0 / &nonzero(&x[1])?.0)))
+            .register("%", 2, |x| Ok(Rational(&x[0].0 % &nonzero(&x[1])?.0)))
+            .register("^", 2, |x| x[0].pow(&x[1]))
+            .register("//", 2, |x| {
+                Ok(Rational((&x[0].0 / &nonzero(&x[1])?.0).floor()))
+            })
+            .register("mod", 2, |x| {
+                let b = &nonzero(&x[1])?.0;
+                let r = &x[0].0 % b;
+                // 余りはいつも 0 以上にする
+                if r.is_negative() {
+                    Ok(Rational(r + b.abs()))
+                } else {
+                    Ok(Rational(r))
+                }
+            })
+            .register_binary("min", Ord::min)
+            .register_binary("max", Ord::max)
+            .register_unary("neg", |a| Rational(-a.0))
+            .register_unary("abs", |a| Rational(a.0.abs()))
+            .register("sqrt", 1, move |x| x[0].root(2).ok_or_else(domain_error))
+            .register_unary("floor", |a| Rational(a.0.floor()))
+            .register_unary("ceil", |a| Rational(a.0.ceil()))
+            .register_unary("round", |a| Rational(a.0.round()))
+            .register("clamp", 3, move |x| {
+                if x[1] <= x[2] {
+                    Ok(x[0].clone().clamp(x[1].clone(), x[2].clone()))
+                } else {
+                    Err(domain_error())
+                }
+            })
+            .set_divisor("/", 1)
+            .set_divisor("%", 1)
+            .set_divisor("//", 1)
+            .set_divisor("mod", 1)
+            .set_variadic("+")
+            .set_variadic("*")
+            .set_variadic("min")
+            .set_variadic("max");
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    // 整数でなければ `1/2` のように割り算として書く
+    fn literal_operator(&self) -> Option<&'static str> {
+        if !self.is_integer() {
+            Some("/")
+        } else if self.0.is_negative() {
+            Some("neg")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pn_as, Expr, Notation, Span};
+
+    #[test]
+    fn rational_test() {
+        let exoressions = [
+            ("+ / 1 3 / 1 6", Ok("1/2")),
+            ("+ 0.1 0.2", Ok("3/10")),
+            ("* 3 / 1 3", Ok("1")),
+            ("- 1 1.5", Ok("-1/2")),
+            ("* 2.5e-3 4", Ok("1/100")),
+            ("+ -.5 1_000", Ok("1999/2")),
+            ("// -7 2", Ok("-4")),
+            ("% -7 2", Ok("-1")),
+            ("mod -7 2", Ok("1")),
+            ("mod 7 -2", Ok("1")),
+            ("^ 2 -2", Ok("1/4")),
+            ("^ / 4 9 0.5", Ok("2/3")),
+            ("^ -8 / 2 3", Ok("4")),
+            ("^ 1 -100000", Ok("1")),
+            ("^ -1 2147483647", Ok("-1")),
+            ("^ / 1 2 -10", Ok("1024")),
+            ("sqrt / 9 16", Ok("3/4")),
+            ("round / 5 2", Ok("3")),
+            ("floor / -5 2", Ok("-3")),
+            ("max / 1 3 0.3", Ok("1/3")),
+            ("clamp 12 0 10", Ok("10")),
+            // 以下エラーテスト
+            (
+                "sqrt 2",
+                Err(PolishError::DomainError {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "sqrt -4",
+                Err(PolishError::DomainError {
+                    operator: Some("sqrt".to_string()),
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "^ 2 0.5",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 2 -2147483648",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 2 / 1 3000000000",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ ^ 10 100000 100000",
+                Err(PolishError::DomainError {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "^ 0 -1",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("^".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "/ 1 0",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("/".to_string()),
+                    span: Some(Span::new(0, 1, 0)),
+                }),
+            ),
+            (
+                "+ 1 inf",
+                Err(PolishError::UndefinedVariable {
+                    name: "inf".to_string(),
+                    span: Some(Span::new(4, 7, 2)),
+                }),
+            ),
+            (
+                "+ 1 1e99999",
+                Err(PolishError::UseUnavailableCharacter {
+                    token: "1e99999".to_string(),
+                    span: Span::new(4, 11, 2),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(
+                pn_as::<Rational>(exoression.0).map(|ans| ans.to_string()),
+                exoression.1.map(str::to_string)
+            );
+        }
+    }
+
+    #[test]
+    fn decimal_test() {
+        let exoressions = [
+            ("/ 1 3", 4, "0.3333"),
+            ("/ 2 3", 2, "0.67"),
+            ("/ -1 8", 2, "-0.13"),
+            ("/ 7 2", 0, "4"),
+            ("-1000", 1, "-1000.0"),
+            ("/ 1 1000", 2, "0.00"),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let ans = pn_as::<Rational>(exoression.0).unwrap();
+            assert_eq!(format!("{:.*}", exoression.1, ans), exoression.2);
+        }
+        assert_eq!(Rational::new(1, 4).unwrap().to_f64(), 0.25);
+        assert_eq!(Rational::from_f64(-0.75), Rational::new(-3, 4));
+        assert_eq!(Rational::from_f64(f64::INFINITY), None);
+        assert_eq!(Rational::new(1, 0), None);
+    }
+
+    #[test]
+    fn notation_test() {
+        let registry = OperatorRegistry::<Rational>::new();
+        let expr = Notation::Infix.parse(&registry, "1/3 + 1/6").unwrap();
+        assert_eq!(expr.eval(), Ok(Rational::new(1, 2).unwrap()));
+        assert_eq!(expr.to_string(), "+ / 1 3 / 1 6");
+
+        // 分数のリテラルは `/` と同じ強さで結び付く
+        let half = Expr::number(Rational::new(1, 2).unwrap());
+        let expr = Expr::operator("^", vec![half.clone(), Expr::number(Rational::from(2))]);
+        assert_eq!(expr.to_infix(), "(1/2) ^ 2");
+        let expr = Expr::operator("*", vec![half.clone(), half]);
+        assert_eq!(expr.to_infix(), "1/2 * (1/2)");
+    }
+}
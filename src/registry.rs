@@ -0,0 +1,387 @@
+use crate::{Number, PolishError};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+type OperatorFn<N> = dyn Fn(&[N]) -> Result<N, PolishError> + Send + Sync;
+
+/// An operator stored in `OperatorRegistry`.
+///
+/// the function always receives exactly `arity` operands, in the order they appear in the expression.
+/// a variadic operator is applied to more operands by folding them from the left,
+/// so `(+ 1 2 3)` is `+ + 1 2 3`.
+pub struct Operator<N = f64> {
+    name: String,
+    arity: usize,
+    divisor: Option<usize>,
+    variadic: bool,
+    func: Box<OperatorFn<N>>,
+}
+
+impl<N: Number> Operator<N> {
+    /// the name this operator is registered as.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the number of operands this operator takes.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// the operand that `Mode::Strict` treats as a divisor, if there is one.
+    pub fn divisor(&self) -> Option<usize> {
+        self.divisor
+    }
+
+    /// whether this operator takes more than `arity` operands in parentheses.
+    pub fn is_variadic(&self) -> bool {
+        self.variadic
+    }
+
+    /// apply this operator to `operands`.
+    ///
+    /// returns `NotEnoughOperands` when there are fewer than `arity` operands,
+    /// and `TooManyOperands` when there are more and this operator is not variadic.
+    /// the errors have no span, evaluating an `Expr` fills in the span of the operator.
+    pub fn apply(&self, operands: &[N]) -> Result<N, PolishError> {
+        if operands.len() < self.arity {
+            Err(PolishError::NotEnoughOperands {
+                operator: self.name.clone(),
+                expected: self.arity,
+                found: operands.len(),
+                span: None,
+            })
+        } else if operands.len() > self.arity && self.variadic {
+            // 左から畳み込む
+            let mut acc = (self.func)(&operands[..2])?;
+            for operand in &operands[2..] {
+                acc = (self.func)(&[acc, operand.clone()])?;
+            }
+            Ok(acc)
+        } else if operands.len() > self.arity {
+            Err(PolishError::TooManyOperands {
+                remaining: operands.len() - self.arity,
+                span: None,
+            })
+        } else {
+            (self.func)(operands)
+        }
+    }
+}
+
+impl<N> fmt::Debug for Operator<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Operator")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("divisor", &self.divisor)
+            .field("variadic", &self.variadic)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A table of the operators `parse_with` and `pn_with` understand.
+///
+/// `OperatorRegistry::new` contains the built-in operators of the `Number` type,
+/// which for `f64` are every operator listed on `pn`, and `OperatorRegistry::empty` contains none.
+/// registering a name that already exists replaces the old operator.
+///
+/// an operator name must not contain whitespace, and a name that is also a number literal
+/// (such as `inf`) is always read as the number.
+/// a registered name is never read as a variable.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::{pn_with, OperatorRegistry};
+///
+/// let mut registry = OperatorRegistry::new();
+/// registry
+///     .register_binary("hypot", f64::hypot)
+///     .register("lerp", 3, |x| Ok(x[0] + (x[1] - x[0]) * x[2]));
+///
+/// assert_eq!(pn_with(&registry, "hypot 3 4"), Ok(5.0));
+/// assert_eq!(pn_with(&registry, "lerp 10 20 0.25"), Ok(12.5));
+/// ```
+#[derive(Debug)]
+pub struct OperatorRegistry<N = f64> {
+    operators: HashMap<String, Operator<N>>,
+}
+
+impl<N: Number> OperatorRegistry<N> {
+    /// create a registry that contains the built-in operators of `N`.
+    pub fn new() -> Self {
+        let mut registry = OperatorRegistry::empty();
+        N::builtins(&mut registry);
+        registry
+    }
+
+    /// create a registry without any operators.
+    pub fn empty() -> Self {
+        OperatorRegistry {
+            operators: HashMap::new(),
+        }
+    }
+
+    /// register `name` as an operator that takes `arity` operands.
+    pub fn register<F>(&mut self, name: &str, arity: usize, func: F) -> &mut Self
+    where
+        F: Fn(&[N]) -> Result<N, PolishError> + Send + Sync + 'static,
+    {
+        self.operators.insert(
+            name.to_string(),
+            Operator {
+                name: name.to_string(),
+                arity,
+                divisor: None,
+                variadic: false,
+                func: Box::new(func),
+            },
+        );
+        self
+    }
+
+    /// register `name` as an operator that takes one operand and cannot fail.
+    pub fn register_unary<F>(&mut self, name: &str, func: F) -> &mut Self
+    where
+        F: Fn(N) -> N + Send + Sync + 'static,
+    {
+        self.register(name, 1, move |x| Ok(func(x[0].clone())))
+    }
+
+    /// register `name` as an operator that takes two operands and cannot fail.
+    pub fn register_binary<F>(&mut self, name: &str, func: F) -> &mut Self
+    where
+        F: Fn(N, N) -> N + Send + Sync + 'static,
+    {
+        self.register(name, 2, move |x| Ok(func(x[0].clone(), x[1].clone())))
+    }
+
+    /// mark operand `index` of `name` as a divisor, so `Mode::Strict` fails with
+    /// `DivisionByZero` when it is zero. does nothing when `name` is not registered.
+    ///
+    /// the built-in `/`, `%`, `//` and `mod` have their second operand marked.
+    pub fn set_divisor(&mut self, name: &str, index: usize) -> &mut Self {
+        if let Some(operator) = self.operators.get_mut(name) {
+            operator.divisor = Some(index);
+        }
+        self
+    }
+
+    /// let `name` take any number of operands, at least two, when it is written in parentheses
+    /// with `Notation::SExpression` or called like a function with `from_infix`.
+    /// the operands are folded from the left. does nothing when `name` is not a binary operator.
+    ///
+    /// the built-in `+`, `*`, `min` and `max` are variadic.
+    pub fn set_variadic(&mut self, name: &str) -> &mut Self {
+        if let Some(operator) = self.operators.get_mut(name) {
+            operator.variadic = operator.arity == 2;
+        }
+        self
+    }
+
+    /// remove `name` from the registry, then return the removed operator.
+    pub fn unregister(&mut self, name: &str) -> Option<Operator<N>> {
+        self.operators.remove(name)
+    }
+
+    /// look up the operator registered as `name`.
+    pub fn get(&self, name: &str) -> Option<&Operator<N>> {
+        self.operators.get(name)
+    }
+
+    /// the number of operands `name` takes, or `None` when it is not registered.
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.get(name).map(Operator::arity)
+    }
+
+    /// whether `name` is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.operators.contains_key(name)
+    }
+
+    /// apply the operator registered as `name` to `operands`.
+    ///
+    /// returns `UnknownOperator` when `name` is not registered.
+    pub fn apply(&self, name: &str, operands: &[N]) -> Result<N, PolishError> {
+        match self.get(name) {
+            Some(operator) => operator.apply(operands),
+            None => Err(PolishError::UnknownOperator {
+                operator: name.to_string(),
+                span: None,
+            }),
+        }
+    }
+}
+
+impl<N: Number> Default for OperatorRegistry<N> {
+    fn default() -> Self {
+        OperatorRegistry::new()
+    }
+}
+
+/// the registry `parse`, `pn` and `Expr::eval` use.
+// 型ごとにひとつだけ作り、プログラムが終わるまで使う
+pub(crate) fn default_registry<N: Number>() -> &'static OperatorRegistry<N> {
+    type Registries = HashMap<TypeId, &'static (dyn Any + Send + Sync)>;
+    static REGISTRIES: OnceLock<Mutex<Registries>> = OnceLock::new();
+    let mut registries = REGISTRIES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let registry = *registries
+        .entry(TypeId::of::<N>())
+        .or_insert_with(|| Box::leak(Box::new(OperatorRegistry::<N>::new())));
+    registry
+        .downcast_ref()
+        .expect("the registry is stored under the TypeId of its number type")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_with, pn, pn_with, Span};
+
+    #[test]
+    fn register_test() {
+        let mut registry = OperatorRegistry::new();
+        registry
+            .register_binary("hypot", f64::hypot)
+            .register("lerp", 3, |x| Ok(x[0] + (x[1] - x[0]) * x[2]))
+            .register("pi", 0, |_| Ok(std::f64::consts::PI))
+            .register("safe/", 2, |x| {
+                if x[1] == 0.0 {
+                    Err(PolishError::DivisionByZero {
+                        operator: None,
+                        span: None,
+                    })
+                } else {
+                    Ok(x[0] / x[1])
+                }
+            });
+        let exoressions = [
+            ("hypot 3 4", Ok(5.0)),
+            ("lerp 10 20 0.5", Ok(15.0)),
+            ("+ 1 lerp 0 hypot 6 8 0.5", Ok(6.0)),
+            ("* 2 pi", Ok(2.0 * std::f64::consts::PI)),
+            ("safe/ 1 2", Ok(0.5)),
+            // 以下エラーテスト
+            // 演算子が返したエラーには演算子の名前と位置が付く
+            (
+                "safe/ 1 0",
+                Err(PolishError::DivisionByZero {
+                    operator: Some("safe/".to_string()),
+                    span: Some(Span::new(0, 5, 0)),
+                }),
+            ),
+            (
+                "lerp 1 2",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "lerp".to_string(),
+                    expected: 3,
+                    found: 2,
+                    span: Some(Span::new(0, 4, 0)),
+                }),
+            ),
+            (
+                "pi 1",
+                Err(PolishError::TooManyOperands {
+                    remaining: 1,
+                    span: Some(Span::new(3, 4, 1)),
+                }),
+            ),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            assert_eq!(pn_with(&registry, exoression.0), exoression.1);
+        }
+        // 既定のレジストリには影響しない
+        assert_eq!(
+            pn("hypot 3 4"),
+            Err(PolishError::TooManyOperands {
+                remaining: 2,
+                span: Some(Span::new(6, 7, 1)),
+            })
+        );
+    }
+
+    #[test]
+    fn variadic_test() {
+        let mut registry = OperatorRegistry::new();
+        registry
+            .register_binary("hypot", f64::hypot)
+            .set_variadic("hypot")
+            .set_variadic("sqrt");
+        assert_eq!(registry.apply("hypot", &[2.0, 3.0, 6.0]), Ok(7.0));
+        assert_eq!(registry.apply("+", &[1.0, 2.0, 3.0, 4.0]), Ok(10.0));
+        assert_eq!(registry.apply("max", &[1.0, 5.0, 3.0]), Ok(5.0));
+        assert!(!registry.get("-").unwrap().is_variadic());
+        assert!(!registry.get("sqrt").unwrap().is_variadic());
+        assert_eq!(
+            registry.apply("sqrt", &[4.0, 9.0]),
+            Err(PolishError::TooManyOperands {
+                remaining: 1,
+                span: None,
+            })
+        );
+    }
+
+    #[test]
+    fn replace_test() {
+        let mut registry = OperatorRegistry::new();
+        registry.register_binary("+", |a, b| a + b + 1.0);
+        assert_eq!(pn_with(&registry, "+ 1 1"), Ok(3.0));
+
+        assert!(registry.unregister("-").is_some());
+        assert!(!registry.contains("-"));
+        assert_eq!(
+            pn_with(&registry, "- 1 1"),
+            Err(PolishError::UseUnavailableCharacter {
+                token: "-".to_string(),
+                span: Span::new(0, 1, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn empty_test() {
+        let mut registry = OperatorRegistry::empty();
+        assert_eq!(pn_with(&registry, "42"), Ok(42.0));
+        assert_eq!(
+            parse_with(&registry, "+ 1 2"),
+            Err(PolishError::UseUnavailableCharacter {
+                token: "+".to_string(),
+                span: Span::new(0, 1, 0),
+            })
+        );
+
+        registry.register_binary("+", |a, b| a + b);
+        assert_eq!(registry.arity("+"), Some(2));
+        assert_eq!(registry.apply("+", &[1.0, 2.0]), Ok(3.0));
+        assert_eq!(
+            registry.apply("+", &[1.0]),
+            Err(PolishError::NotEnoughOperands {
+                operator: "+".to_string(),
+                expected: 2,
+                found: 1,
+                span: None,
+            })
+        );
+        assert_eq!(
+            registry.apply("*", &[1.0, 2.0]),
+            Err(PolishError::UnknownOperator {
+                operator: "*".to_string(),
+                span: None,
+            })
+        );
+        assert_eq!(
+            registry.apply("+", &[1.0, 2.0, 3.0]),
+            Err(PolishError::TooManyOperands {
+                remaining: 1,
+                span: None,
+            })
+        );
+    }
+}
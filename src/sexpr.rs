@@ -0,0 +1,431 @@
+use crate::{
+    parse_token, render_with, Expr, ExprKind, Number, Operator, OperatorRegistry, Piece,
+    PolishError, Span, Token, TokenKind,
+};
+
+// 空白と括弧で区切り、括弧もひとつのトークンにする
+fn tokenize(expression: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in expression.char_indices() {
+        let paren = c == '(' || c == ')';
+        if c.is_whitespace() || paren {
+            if let Some(s) = start.take() {
+                let span = Span::new(s, i, tokens.len());
+                tokens.push(Token {
+                    text: &expression[s..i],
+                    span,
+                });
+            }
+            if paren {
+                let span = Span::new(i, i + 1, tokens.len());
+                tokens.push(Token {
+                    text: &expression[i..i + 1],
+                    span,
+                });
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        let span = Span::new(s, expression.len(), tokens.len());
+        tokens.push(Token {
+            text: &expression[s..],
+            span,
+        });
+    }
+    tokens
+}
+
+struct Parser<'a, 'r, N> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    registry: &'r OperatorRegistry<N>,
+}
+
+// 被演算子を読んでいる途中の式
+enum Frame<N> {
+    // 括弧のない演算子。決まった数の被演算子を取る
+    Bare {
+        op: String,
+        arity: usize,
+        span: Span,
+        children: Vec<Expr<N>>,
+    },
+    // 括弧で囲んだ演算子。`)` までの被演算子を取る
+    Group {
+        op: String,
+        open: Span,
+        span: Span,
+        children: Vec<Expr<N>>,
+        // 被演算子が多すぎるときに、最初の余分な被演算子を指す
+        extra: Option<Span>,
+    },
+    // 余分な括弧で囲んだだけの式
+    Paren {
+        open: Span,
+        inner: Option<Expr<N>>,
+    },
+}
+
+impl<N: Number> Parser<'_, '_, N> {
+    // 数値・変数、括弧で囲んだ式、または括弧のない前置記法の式をひとつ読む。
+    // 深い入れ子でもスタックが溢れないよう、読みかけの式は `frames` に積む
+    fn expr(&mut self) -> Result<Expr<N>, PolishError> {
+        let mut frames: Vec<Frame<N>> = vec![];
+        loop {
+            let mut done = self.begin(&mut frames)?;
+            // 読み終えた式を親に渡し、親も読み終えたらさらにその親に渡す
+            loop {
+                let Some(frame) = frames.last_mut() else {
+                    if let Some(expr) = done {
+                        return Ok(expr);
+                    }
+                    break;
+                };
+                if let Some(expr) = done.take() {
+                    match frame {
+                        Frame::Bare { children, .. } | Frame::Group { children, .. } => {
+                            children.push(expr)
+                        }
+                        Frame::Paren { inner, .. } => *inner = Some(expr),
+                    }
+                }
+                match self.finish(frame)? {
+                    Some(expr) => {
+                        frames.pop();
+                        done = Some(expr);
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    // 式の最初のトークンを読む。数値か変数ならその式を返し、演算子か括弧なら `frames` に積む
+    fn begin(&mut self, frames: &mut Vec<Frame<N>>) -> Result<Option<Expr<N>>, PolishError> {
+        let token = &self.tokens[self.pos];
+        let span = token.span;
+        self.pos += 1;
+        let kind = match token.text {
+            "(" => {
+                frames.push(self.group(span)?);
+                return Ok(None);
+            }
+            ")" => return Err(PolishError::UnbalancedParenthesis { span }),
+            _ => match parse_token(token, self.registry)? {
+                TokenKind::Operand(opnd) => ExprKind::Number(opnd),
+                TokenKind::Variable(name) => ExprKind::Variable(name),
+                // 括弧がなければ決まった数の被演算子を取る
+                TokenKind::Operator(ops) => {
+                    frames.push(Frame::Bare {
+                        arity: self.registry.arity(&ops).unwrap_or_default(),
+                        op: ops,
+                        span,
+                        children: vec![],
+                    });
+                    return Ok(None);
+                }
+            },
+        };
+        Ok(Some(Expr {
+            kind,
+            span: Some(span),
+        }))
+    }
+
+    // `(` の次を読み、括弧の中身に応じた枠を作る
+    fn group(&mut self, open: Span) -> Result<Frame<N>, PolishError> {
+        let Some(head) = self.tokens.get(self.pos) else {
+            return Err(PolishError::UnbalancedParenthesis { span: open });
+        };
+        let head_span = head.span;
+        let op = match head.text {
+            "(" => None,
+            ")" => {
+                return Err(PolishError::UnexpectedToken {
+                    token: head.text.to_string(),
+                    span: head_span,
+                })
+            }
+            _ => match parse_token(head, self.registry)? {
+                TokenKind::Operator(ops) => Some(ops),
+                // 関数のように呼ばれた名前は演算子のはず
+                TokenKind::Variable(name)
+                    if self.tokens.get(self.pos + 1).is_some_and(|t| t.text != ")") =>
+                {
+                    return Err(PolishError::UnknownOperator {
+                        operator: name,
+                        span: Some(head_span),
+                    });
+                }
+                _ => None,
+            },
+        };
+        let Some(op) = op else {
+            return Ok(Frame::Paren { open, inner: None });
+        };
+        self.pos += 1;
+        Ok(Frame::Group {
+            op,
+            open,
+            span: head_span,
+            children: vec![],
+            extra: None,
+        })
+    }
+
+    // 次のトークンを見て、枠の式を読み終えたならその式を返す。`None` なら次の被演算子を読む
+    fn finish(&mut self, frame: &mut Frame<N>) -> Result<Option<Expr<N>>, PolishError> {
+        match frame {
+            Frame::Bare {
+                op,
+                arity,
+                span,
+                children,
+            } => {
+                if children.len() == *arity {
+                    return Ok(Some(Expr {
+                        kind: ExprKind::Operator {
+                            op: std::mem::take(op),
+                            children: std::mem::take(children),
+                        },
+                        span: Some(*span),
+                    }));
+                }
+                match self.tokens.get(self.pos) {
+                    Some(token) if token.text != ")" => Ok(None),
+                    _ => Err(PolishError::NotEnoughOperands {
+                        operator: std::mem::take(op),
+                        expected: *arity,
+                        found: children.len(),
+                        span: Some(*span),
+                    }),
+                }
+            }
+            Frame::Group {
+                op,
+                open,
+                span,
+                children,
+                extra,
+            } => {
+                let operator = self.registry.get(op);
+                let arity = operator.map_or(0, Operator::arity);
+                match self.tokens.get(self.pos) {
+                    None => Err(PolishError::UnbalancedParenthesis { span: *open }),
+                    Some(token) if token.text == ")" => {
+                        self.pos += 1;
+                        if children.len() < arity {
+                            return Err(PolishError::NotEnoughOperands {
+                                operator: std::mem::take(op),
+                                expected: arity,
+                                found: children.len(),
+                                span: Some(*span),
+                            });
+                        }
+                        if children.len() > arity && !operator.is_some_and(Operator::is_variadic) {
+                            return Err(PolishError::TooManyOperands {
+                                remaining: children.len() - arity,
+                                span: *extra,
+                            });
+                        }
+                        Ok(Some(Expr {
+                            kind: ExprKind::Operator {
+                                op: std::mem::take(op),
+                                children: std::mem::take(children),
+                            },
+                            span: Some(*span),
+                        }))
+                    }
+                    Some(token) => {
+                        if children.len() == arity && extra.is_none() {
+                            *extra = Some(token.span);
+                        }
+                        Ok(None)
+                    }
+                }
+            }
+            Frame::Paren { open, inner } => {
+                let Some(expr) = inner.take() else {
+                    return Ok(None);
+                };
+                match self.tokens.get(self.pos) {
+                    Some(token) if token.text == ")" => {
+                        self.pos += 1;
+                        Ok(Some(expr))
+                    }
+                    Some(token) => Err(PolishError::UnexpectedToken {
+                        token: token.text.to_string(),
+                        span: token.span,
+                    }),
+                    None => Err(PolishError::UnbalancedParenthesis { span: *open }),
+                }
+            }
+        }
+    }
+}
+
+// Cambridge Polish notation を読む
+//
+// `(+ 5 (* 2 3))` のように演算子と被演算子を括弧で囲む。括弧は省略でき、`+ 5 (* 2 3)` と書いてもよい。
+// 括弧で囲んだ可変長の演算子は、いくつでも被演算子を取る
+pub(crate) fn parse_sexpr<N: Number>(
+    registry: &OperatorRegistry<N>,
+    expression: &str,
+) -> Result<Expr<N>, PolishError> {
+    let tokens = tokenize(expression);
+    if tokens.is_empty() {
+        return Err(PolishError::NotEnteredExoression);
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        registry,
+    };
+    let expr = parser.expr()?;
+    if parser.pos == parser.tokens.len() {
+        return Ok(expr);
+    }
+
+    // 余った式を数える
+    let span = parser.tokens[parser.pos].span;
+    let mut remaining = 0;
+    while parser.pos < parser.tokens.len() {
+        parser.expr()?;
+        remaining += 1;
+    }
+    Err(PolishError::TooManyOperands {
+        remaining,
+        span: Some(span),
+    })
+}
+
+pub(crate) fn write_sexpr<N: Number>(expr: &Expr<N>) -> String {
+    render_with(expr, |expr, pieces| match &expr.kind {
+        ExprKind::Operator { op, children } => {
+            pieces.push(Piece::Text(format!("({}", op)));
+            for child in children {
+                pieces.push(Piece::Text(" ".to_string()));
+                pieces.push(Piece::Expr(child));
+            }
+            pieces.push(Piece::Text(")".to_string()));
+        }
+        _ => pieces.push(Piece::Text(expr.token())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sexpr_test() {
+        let registry = OperatorRegistry::<f64>::new();
+        let exoressions = [
+            ("(+ (* 2 (- 3 1)) 4)", Ok("+ * 2 - 3 1 4")),
+            ("(((+ 1 2)))", Ok("+ 1 2")),
+            ("(neg (neg (neg 1)))", Ok("neg neg neg 1")),
+            ("(- (+ 1 2) (* 3 4))", Ok("- + 1 2 * 3 4")),
+            ("(+ 1 2 3 (max 4 5 6))", Ok("+ + + 1 2 3 max max 4 5 6")),
+            // 括弧のない式と括弧で囲んだ式は混ぜられる
+            ("(* + 1 2 (- 3 4))", Ok("* + 1 2 - 3 4")),
+            ("- (+ 1 2 3) * 4 5", Ok("- + + 1 2 3 * 4 5")),
+            ("((x))", Ok("x")),
+            // 以下エラーテスト
+            (
+                "((+ 1 2)",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "(+ 1 (* 2 3)",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                ")",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(0, 1, 0),
+                }),
+            ),
+            (
+                "(+ 1 2) )",
+                Err(PolishError::UnbalancedParenthesis {
+                    span: Span::new(8, 9, 5),
+                }),
+            ),
+            (
+                "((1) 2)",
+                Err(PolishError::UnexpectedToken {
+                    token: "2".to_string(),
+                    span: Span::new(5, 6, 4),
+                }),
+            ),
+            (
+                "(+ (- 1) 2)",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "-".to_string(),
+                    expected: 2,
+                    found: 1,
+                    span: Some(Span::new(4, 5, 3)),
+                }),
+            ),
+            (
+                "(max)",
+                Err(PolishError::NotEnoughOperands {
+                    operator: "max".to_string(),
+                    expected: 2,
+                    found: 0,
+                    span: Some(Span::new(1, 4, 1)),
+                }),
+            ),
+            (
+                "(sqrt 4 (+ 1 2) 9)",
+                Err(PolishError::TooManyOperands {
+                    remaining: 2,
+                    span: Some(Span::new(8, 9, 3)),
+                }),
+            ),
+            (
+                "(* 2 (f 1))",
+                Err(PolishError::UnknownOperator {
+                    operator: "f".to_string(),
+                    span: Some(Span::new(6, 7, 4)),
+                }),
+            ),
+            ("  \n", Err(PolishError::NotEnteredExoression)),
+        ];
+        for exoression in exoressions {
+            println!("{:?}", exoression);
+            let expr = parse_sexpr(&registry, exoression.0);
+            assert_eq!(
+                expr.map(|expr| expr.to_string()),
+                exoression.1.map(str::to_string)
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_test() {
+        let registry = OperatorRegistry::<f64>::new();
+        // 深い入れ子でもスタックが溢れない
+        let depth = 10_000;
+        let sexpr = format!("{}1{}", "(neg ".repeat(depth), ")".repeat(depth));
+        let expr = parse_sexpr(&registry, &sexpr).unwrap();
+        assert_eq!(expr.eval(), Ok(1.0));
+        assert_eq!(write_sexpr(&expr), sexpr);
+
+        // 閉じていない括弧は、いちばん内側のものを指す
+        let open = format!("{}1", "(+ 1 ".repeat(depth));
+        assert_eq!(
+            parse_sexpr(&registry, &open),
+            Err(PolishError::UnbalancedParenthesis {
+                span: Span::new(5 * (depth - 1), 5 * (depth - 1) + 1, 3 * (depth - 1)),
+            })
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{EvalObserver, Evaluator, Expr, Number, PolishError, Span};
+use std::fmt;
+
+/// One token of a step-by-step evaluation, made by `pn_trace`.
+///
+/// for a number or variable, `operator` is `None`, `operands` is empty and `result` is its value.
+/// for an operator, `operands` are the values it took off the stack,
+/// in the order they appear in the exoression.
+/// `stack` is the operand stack after the step, with the top last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step<N = f64> {
+    pub token: String,
+    pub span: Option<Span>,
+    pub operator: Option<String>,
+    pub operands: Vec<N>,
+    pub result: N,
+    pub stack: Vec<N>,
+}
+
+impl<N: fmt::Display + fmt::Debug> fmt::Display for Step<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.operator {
+            Some(operator) => {
+                write!(f, "{}", operator)?;
+                for operand in &self.operands {
+                    write!(f, " {}", operand)?;
+                }
+                write!(f, " = {}", self.result)?;
+            }
+            None => write!(f, "push {}", self.token)?,
+        }
+        write!(f, "    stack: {:?}", self.stack)
+    }
+}
+
+// イベントを 1 トークンずつの Step にまとめる
+struct StepRecorder<N> {
+    steps: Vec<Step<N>>,
+    // 読んだトークンと、演算子なら取った被演算子
+    token: Option<(String, Option<Span>)>,
+    applied: Option<(String, Vec<N>)>,
+}
+
+impl<N: Number> EvalObserver<N> for StepRecorder<N> {
+    fn on_token(&mut self, expr: &Expr<N>) {
+        self.token = Some((expr.token(), expr.span));
+        self.applied = None;
+    }
+
+    fn on_apply(&mut self, operator: &str, operands: &[N], _result: N) {
+        self.applied = Some((operator.to_string(), operands.to_vec()));
+    }
+
+    fn on_push(&mut self, value: N, stack: &[N]) {
+        if let Some((token, span)) = self.token.take() {
+            let (operator, operands) = match self.applied.take() {
+                Some((operator, operands)) => (Some(operator), operands),
+                None => (None, vec![]),
+            };
+            self.steps.push(Step {
+                token,
+                span,
+                operator,
+                operands,
+                result: value,
+                stack: stack.to_vec(),
+            });
+        }
+    }
+}
+
+impl<N: Number> Evaluator<'_, N> {
+    /// parse `expression`, then evaluate it and return every step.
+    ///
+    /// the last step holds the answer.
+    pub fn pn_trace(&self, expression: &str) -> Result<Vec<Step<N>>, PolishError> {
+        let mut recorder = StepRecorder {
+            steps: vec![],
+            token: None,
+            applied: None,
+        };
+        self.pn_observed(expression, &mut recorder)?;
+        Ok(recorder.steps)
+    }
+}
+
+/// receive an exoression in Polish notation as `&str`, then `pn_trace` return every step of the calculation
+/// as `Vec<Step>` or `PolishError`.
+///
+/// each step is one token read from right to left, with the operand stack after it.
+/// an `Evaluator` with `Notation::Postfix` or `Notation::Infix` reads the tokens from left to right instead.
+/// use `Evaluator::pn_trace` for variables, your own operators or `Mode::Strict`.
+///
+/// ## example
+///
+/// ```
+/// use polish_notation::pn_trace;
+///
+/// let steps = pn_trace("* + 5 1 2").unwrap();
+/// let work: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+/// assert_eq!(
+///     work,
+///     [
+///         "push 2    stack: [2.0]",
+///         "push 1    stack: [2.0, 1.0]",
+///         "push 5    stack: [2.0, 1.0, 5.0]",
+///         "+ 5 1 = 6    stack: [2.0, 6.0]",
+///         "* 6 2 = 12    stack: [12.0]",
+///     ]
+/// );
+/// assert_eq!(steps.last().unwrap().result, 12.0);
+/// ```
+pub fn pn_trace(expression: &str) -> Result<Vec<Step>, PolishError> {
+    Evaluator::new().pn_trace(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Environment, Mode};
+
+    #[test]
+    fn step_test() {
+        let error = pn_trace("clamp x 0 sqrt 4").unwrap_err();
+        assert_eq!(error.code(), "E0005");
+
+        let mut env = Environment::new();
+        env.set("x", 3.0);
+        let steps = Evaluator::new()
+            .env(&env)
+            .pn_trace("clamp x 0 sqrt 4")
+            .unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step {
+                    token: "4".to_string(),
+                    span: Some(Span::new(15, 16, 4)),
+                    operator: None,
+                    operands: vec![],
+                    result: 4.0,
+                    stack: vec![4.0],
+                },
+                Step {
+                    token: "sqrt".to_string(),
+                    span: Some(Span::new(10, 14, 3)),
+                    operator: Some("sqrt".to_string()),
+                    operands: vec![4.0],
+                    result: 2.0,
+                    stack: vec![2.0],
+                },
+                Step {
+                    token: "0".to_string(),
+                    span: Some(Span::new(8, 9, 2)),
+                    operator: None,
+                    operands: vec![],
+                    result: 0.0,
+                    stack: vec![2.0, 0.0],
+                },
+                Step {
+                    token: "x".to_string(),
+                    span: Some(Span::new(6, 7, 1)),
+                    operator: None,
+                    operands: vec![],
+                    result: 3.0,
+                    stack: vec![2.0, 0.0, 3.0],
+                },
+                Step {
+                    token: "clamp".to_string(),
+                    span: Some(Span::new(0, 5, 0)),
+                    operator: Some("clamp".to_string()),
+                    operands: vec![3.0, 0.0, 2.0],
+                    result: 2.0,
+                    stack: vec![2.0],
+                },
+            ]
+        );
+        assert_eq!(steps[3].to_string(), "push x    stack: [2.0, 0.0, 3.0]");
+    }
+
+    #[test]
+    fn error_test() {
+        assert!(matches!(
+            pn_trace("+ 1"),
+            Err(PolishError::NotEnoughOperands { .. })
+        ));
+        assert_eq!(pn_trace("/ 1 0").unwrap().len(), 3);
+        assert!(matches!(
+            Evaluator::new().mode(Mode::Strict).pn_trace("/ 1 0"),
+            Err(PolishError::DivisionByZero { .. })
+        ));
+    }
+}